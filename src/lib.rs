@@ -0,0 +1,13 @@
+//! Regular expression validator built on a Thompson automaton
+//!
+//! ```
+//! use vregex::Vregex;
+//!
+//! let mut vregex = Vregex::new("a*").unwrap();
+//! assert!(vregex.validate("aaa").unwrap());
+//! assert!(!vregex.validate("ab").unwrap());
+//! ```
+
+pub mod vregex;
+
+pub use vregex::{Automaton, AutomatonError, StateMachine, StateMachineError, Vregex, VregexError, VregexOptions};
@@ -1,112 +1,1183 @@
 mod automaton;
+mod bytes;
+mod lexer;
 mod regex_parsing;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+// The engine is generic over the symbol type, Vregex being the char based front end with a parser
+pub use automaton::{Automaton, AutomatonBuilder, AutomatonError, Cursor, Diagnostics, GroupTag, Outgoing, StateId, StateMachine, StateMachineError};
+pub use bytes::ByteRegex;
+pub use lexer::{Lexer, Token};
+pub use regex_parsing::Regex;
+
+use std::fmt;
 
 #[derive(Debug)]
 pub enum VregexError {
     InternalFailure,
-    ParsingError,
+    // offset is the byte offset in the regex where parsing failed
+    ParsingError { offset: usize, message: String },
+    // Same as ParsingError for the pattern of that index in a list, see Vregex::any_of()
+    PatternError { index: usize, offset: usize, message: String },
+    // A hand built Regex the parser would never produce, like a repetition with min > max
+    InvalidAst { message: String },
+    // The automaton would have more states than allowed, see Vregex::with_state_limit()
+    StateLimitExceeded { limit: usize },
+    // Matching took more steps than allowed, see Vregex::validate_bounded()
+    Budget { max_steps: usize },
+}
+
+impl fmt::Display for VregexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VregexError::InternalFailure => write!(f, "Internal failure"),
+            VregexError::ParsingError { offset, message } => write!(f, "Parsing error at {}: {}", offset, message),
+            VregexError::PatternError { index, offset, message } => {
+                write!(f, "Parsing error in pattern {} at {}: {}", index, offset, message)
+            }
+            VregexError::InvalidAst { message } => write!(f, "Invalid regex tree: {}", message),
+            VregexError::StateLimitExceeded { limit } => write!(f, "Automaton exceeds the limit of {} states", limit),
+            VregexError::Budget { max_steps } => write!(f, "Matching exceeds the budget of {} steps", max_steps),
+        }
+    }
 }
 
+impl std::error::Error for VregexError {}
+
 impl From<automaton::AutomatonError> for VregexError {
     fn from(_: automaton::AutomatonError) -> Self {
         VregexError::InternalFailure
     }
 }
-impl From<String> for VregexError {
-    fn from(_: String) -> Self {
-        VregexError::ParsingError
+impl From<regex_parsing::ParseError> for VregexError {
+    fn from(e: regex_parsing::ParseError) -> Self {
+        VregexError::ParsingError { offset: e.offset, message: e.message }
+    }
+}
+
+// Symbols the wildcard . and negated classes can match when no universe is given: the ones the parser accepts as literals
+pub fn default_universe() -> HashSet<char> {
+    ('a'..='z').chain('A'..='Z').chain('0'..='9').collect()
+}
+
+// Which match Vregex::find_with() picks among the ones starting at the leftmost possible position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchPolicy {
+    // The longest one, as find() does
+    LeftmostLongest,
+    // The shortest one, e.g. (0, 0) for a* on "aaa"
+    LeftmostShortest,
+}
+
+// Unicode normalization applied by VregexOptions::normalize()
+#[cfg(feature = "normalization")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    // Canonical composition, e.g. "e\u{301}" becomes "\u{e9}"
+    Nfc,
+}
+
+// Build settings of Vregex::new_with_options(), a subset of VregexOptions
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // Whether . matches '\n', off by default as in most regex engines
+    // When on, '\n' is added to the universe, so negated classes match it too
+    pub dot_matches_newline: bool,
+}
+
+// Build settings of Vregex::with_options(), everything off by default, e.g.
+// VregexOptions::new().case_insensitive(true).state_limit(1000)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VregexOptions {
+    case_insensitive: bool,
+    extended: bool,
+    dot_matches_newline: bool,
+    state_limit: Option<usize>,
+    #[cfg(feature = "normalization")]
+    normalize: Option<Normalization>,
+}
+
+impl VregexOptions {
+    pub fn new() -> VregexOptions {
+        VregexOptions::default()
+    }
+
+    // ASCII letters match regardless of case, in literals as well as in classes
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    // Unescaped spaces and tabs in the regex are ignored, e.g. "( ab )+ c" is (ab)+c
+    // A literal space is written "\\ " (or \x20)
+    pub fn extended(mut self, yes: bool) -> Self {
+        self.extended = yes;
+        self
+    }
+
+    // . matches '\n', which is then added to the universe so negated classes match it too
+    // Otherwise . never matches '\n' as in most regex engines, even if the regex writes it elsewhere
+    pub fn dot_matches_newline(mut self, yes: bool) -> Self {
+        self.dot_matches_newline = yes;
+        self
+    }
+
+    // Fails with StateLimitExceeded instead of building an automaton of more than limit states,
+    // e.g. for a{1000}{1000}, the build stopping as soon as the limit is crossed
+    pub fn state_limit(mut self, limit: usize) -> Self {
+        self.state_limit = Some(limit);
+        self
+    }
+
+    // The strings read whole are normalized to form first, so that the composed and decomposed forms of an accented
+    // letter match the same way: by validate() and matcher(), validate_bounded(), validate_which(), is_match(),
+    // is_prefix(), stream_validate() and validate_reader()
+    // The regex is left as is since the parser only reads ASCII and \xHH escapes, which are all in NFC already
+    // Methods giving offsets or a result per char (find(), find_overlapping(), captures(), trace(), feed()...) read
+    // the input as given, their results would not apply to it otherwise
+    #[cfg(feature = "normalization")]
+    pub fn normalize(mut self, form: Normalization) -> Self {
+        self.normalize = Some(form);
+        self
     }
 }
 
+// Most strings Vregex::enumerate() yields
+const MAX_ENUMERATED: usize = 10_000;
+
 #[derive(Debug)]
 pub struct Vregex {
-    pub automaton: automaton::Automaton<char>,
+    // Private so that it always is the automaton built for the regex, with its entry state, see automaton()
+    automaton: automaton::Automaton<char>,
+    // The configured universe plus every symbol written in the regex
+    universe: HashSet<char>,
+    // States where a capture group starts or ends, see captures()
+    tags: HashMap<usize, automaton::GroupTag>,
+    nb_groups: usize,
+    // Index of every group named with (?<name>...), see captures_named()
+    names: HashMap<String, usize>,
+    // Whether the regex starts with ^ or ends with $, only searches care since validate() reads the whole input anyway
+    anchored_start: bool,
+    anchored_end: bool,
+    // Whether feed() has started a stream that finish() has not ended yet
+    streaming: bool,
+    #[cfg(feature = "normalization")]
+    normalize: Option<Normalization>,
 }
 
 impl Vregex {
-    // Give the regex here
-    pub fn new(regex: &str) -> Self {
-        let mut out = Vregex {
-            automaton: automaton::Automaton::new(),
+    /// Builds the regex, failing with ParsingError if it is invalid
+    ///
+    /// ```
+    /// use vregex::{Vregex, VregexError};
+    ///
+    /// assert!(Vregex::new("(ab)*c").is_ok());
+    /// assert!(matches!(Vregex::new("a("), Err(VregexError::ParsingError { offset: 1, .. })));
+    /// ```
+    pub fn new(regex: &str) -> Result<Vregex, VregexError> {
+        Vregex::with_options(regex, VregexOptions::new())
+    }
+
+    // Same as new() with the settings of options, e.g. a.b matching "a\nb" only with dot_matches_newline
+    pub fn with_options(regex: &str, options: VregexOptions) -> Result<Vregex, VregexError> {
+        let mut reg = if options.extended {
+            regex_parsing::parse_regex_extended(regex)?
+        } else {
+            regex_parsing::parse_regex(regex)?
         };
-        let _ = out.build(regex);
-        out
+        if options.case_insensitive {
+            reg = reg.ignore_ascii_case();
+        }
+        let mut universe = default_universe();
+        if options.dot_matches_newline {
+            universe.insert('\n');
+        }
+        let mut out = Vregex::empty(universe);
+        #[cfg(feature = "normalization")]
+        {
+            out.normalize = options.normalize;
+        }
+        out.build_ast(reg, options.state_limit, options.dot_matches_newline)?;
+        Ok(out)
     }
 
-    // Takes s and return if s is in the language defined by the regex given in new()
-    pub fn validate(&mut self, s: &str) -> bool {
-        self.automaton.init_for_read();
-        if s.is_empty() {
+    // Same as with_options() with the settings of options, see VregexOptions::dot_matches_newline()
+    pub fn new_with_options(regex: &str, options: Options) -> Result<Vregex, VregexError> {
+        Vregex::with_options(regex, VregexOptions::new().dot_matches_newline(options.dot_matches_newline))
+    }
+
+    // Same as new() but the wildcard . and negated classes like [^a] match the symbols of universe
+    // (and the ones written in the regex) instead of the default_universe()
+    // With an empty universe and no symbol in the regex, . matches nothing, and '\n' is matched by . if it is in universe
+    pub fn with_universe(regex: &str, universe: impl IntoIterator<Item = char>) -> Result<Vregex, VregexError> {
+        let mut out = Vregex::empty(universe.into_iter().collect());
+        out.build_ast(regex_parsing::parse_regex(regex)?, None, true)?;
+        Ok(out)
+    }
+
+    // Same as with_options() with only VregexOptions::state_limit() set
+    pub fn with_state_limit(regex: &str, limit: usize) -> Result<Vregex, VregexError> {
+        Vregex::with_options(regex, VregexOptions::new().state_limit(limit))
+    }
+
+    // Same as with_options() with only VregexOptions::case_insensitive() set
+    pub fn new_case_insensitive(regex: &str) -> Result<Vregex, VregexError> {
+        Vregex::with_options(regex, VregexOptions::new().case_insensitive(true))
+    }
+
+    // Same as with_options() with only VregexOptions::extended() set
+    pub fn new_extended(regex: &str) -> Result<Vregex, VregexError> {
+        Vregex::with_options(regex, VregexOptions::new().extended(true))
+    }
+
+    // Builds the automaton straight from a Regex tree, skipping the parser
+    // Uses the default_universe() like new()
+    pub fn from_ast(ast: Regex) -> Result<Vregex, VregexError> {
+        let mut out = Vregex::empty(default_universe());
+        out.build_ast(ast, None, false)?;
+        Ok(out)
+    }
+
+    // Regex accepting the strings accepted by any of patterns, e.g. for a list read from a config file
+    // They share one universe, so . in one of them also matches the symbols written in the others
+    // Fails with PatternError giving the index of the first pattern that does not parse, or of the first one anchored
+    // differently from the previous ones since the anchors of a Vregex apply to all of it
+    // Capture groups are lost like with intersect(), and an empty list matches nothing
+    pub fn any_of(patterns: &[&str]) -> Result<Vregex, VregexError> {
+        let mut asts = Vec::with_capacity(patterns.len());
+        for (index, pattern) in patterns.iter().enumerate() {
+            let reg = regex_parsing::parse_regex(pattern)
+                .map_err(|e| VregexError::PatternError { index, offset: e.offset, message: e.message })?;
+            asts.push(reg);
+        }
+        let mut universe = default_universe();
+        for reg in asts.iter() {
+            universe.extend(reg.symbols());
+        }
+        let mut out = Vregex::empty(universe.clone());
+        for (index, reg) in asts.into_iter().enumerate() {
+            let mut part = Vregex::empty(universe.clone());
+            part.build_ast(reg, None, false)?;
+            let anchors = (part.anchored_start, part.anchored_end);
+            if index > 0 && anchors != (out.anchored_start, out.anchored_end) {
+                let message = "anchors differ from the previous patterns".to_string();
+                return Err(VregexError::PatternError { index, offset: 0, message });
+            }
+            (out.anchored_start, out.anchored_end) = anchors;
+            out.automaton = out.automaton.union(&part.automaton);
+        }
+        out.automaton.finalize();
+        Ok(out)
+    }
+
+    fn empty(universe: HashSet<char>) -> Self {
+        Vregex {
+            automaton: automaton::Automaton::new(),
+            universe,
+            tags: HashMap::new(),
+            nb_groups: 0,
+            names: HashMap::new(),
+            anchored_start: false,
+            anchored_end: false,
+            streaming: false,
+            #[cfg(feature = "normalization")]
+            normalize: None,
+        }
+    }
+
+    // The automaton built for the regex, e.g. to save it with Automaton::to_bytes()
+    pub fn automaton(&self) -> &automaton::Automaton<char> {
+        &self.automaton
+    }
+
+    pub fn universe(&self) -> &HashSet<char> {
+        &self.universe
+    }
+
+    // Chars the compiled regex can actually read, a subset of the universe
+    // e.g. {'a', 'b'} for a+b, while . gives the whole universe
+    pub fn alphabet(&self) -> HashSet<char> {
+        self.automaton.alphabet()
+    }
+
+    /// Whether the whole of s is in the language of the regex
+    ///
+    /// ```
+    /// use vregex::Vregex;
+    ///
+    /// // + is alternation, the whole string has to match
+    /// let mut vregex = Vregex::new("a+b{2}").unwrap();
+    /// assert!(vregex.validate("a").unwrap());
+    /// assert!(vregex.validate("bb").unwrap());
+    /// assert!(!vregex.validate("abb").unwrap());
+    /// ```
+    // Stops at the first char no run survives, e.g. at the X of abXc for abc
+    // Fails with InternalFailure if the automaton is malformed
+    // Matching is per Unicode scalar value (char), so "e\u{301}" is two chars and does not match the single \xE9,
+    // unless VregexOptions::normalize() was set
+    pub fn validate(&mut self, s: &str) -> Result<bool, VregexError> {
+        self.matcher().validate(s)
+    }
+
+    // s as read by the methods reading it whole, normalized if VregexOptions::normalize() was set
+    fn normalized<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "normalization")]
+        if let Some(form) = self.normalize {
+            return normalize(s, form);
+        }
+        Cow::Borrowed(s)
+    }
+
+    // Same as validate() but fails with Budget once more than max_steps steps were spent,
+    // reading a char costing one step per current state
+    // Meant for untrusted patterns and inputs, where the cost of a read grows with the number of live states
+    pub fn validate_bounded(&mut self, s: &str, max_steps: usize) -> Result<bool, VregexError> {
+        let s = self.normalized(s);
+        self.automaton.init_for_read()?;
+        self.automaton.read_empty();
+        let mut steps: usize = 0;
+        for c in s.chars() {
+            if !self.automaton.is_alive() {
+                return Ok(false);
+            }
+            steps = steps.saturating_add(self.automaton.nb_current_states());
+            if steps > max_steps {
+                return Err(VregexError::Budget { max_steps });
+            }
+            self.automaton.read(&c);
+        }
+        Ok(self.automaton.on_exit())
+    }
+
+    // Exit states reached after reading the whole of s, empty iff validate() would reject s
+    // The automaton of a union keeps the exits of each side, so mapping them back to the united patterns
+    // tells which one matched
+    // A malformed automaton reaches no exit
+    pub fn validate_which(&mut self, s: &str) -> Vec<usize> {
+        let s = self.normalized(s);
+        if self.automaton.init_for_read().is_err() {
+            return Vec::new();
+        }
+        self.automaton.read_empty();
+        for c in s.chars() {
+            self.automaton.read(&c);
+        }
+        self.automaton.exits_reached()
+    }
+
+    // States the automaton is in before reading s and after each of its chars, so one more set than chars
+    // The run goes on with empty sets once it is dead, and a malformed automaton gives no set at all
+    pub fn trace(&mut self, s: &str) -> Vec<HashSet<usize>> {
+        if self.automaton.init_for_read().is_err() {
+            return Vec::new();
+        }
+        self.automaton.read_empty();
+        let mut sets = Vec::with_capacity(s.len() + 1);
+        sets.push(self.automaton.get_current_states());
+        for c in s.chars() {
+            sets.push(self.automaton.trace_read(&c));
+        }
+        sets
+    }
+
+    // Same as validate() for input that is not a &str, e.g. chars decoded from a reader
+    // Stops pulling from it as soon as no match is possible anymore
+    // A malformed automaton matches nothing
+    pub fn stream_validate<I: Iterator<Item = char>>(&mut self, it: I) -> bool {
+        #[cfg(feature = "normalization")]
+        if let Some(Normalization::Nfc) = self.normalize {
+            return self.read_stream(unicode_normalization::UnicodeNormalization::nfc(it));
+        }
+        self.read_stream(it)
+    }
+
+    // stream_validate() once the chars are normalized
+    fn read_stream<I: Iterator<Item = char>>(&mut self, it: I) -> bool {
+        if self.automaton.init_for_read().is_err() {
+            return false;
+        }
+        self.automaton.read_empty();
+        for c in it {
+            self.automaton.read(&c);
+            if !self.automaton.is_alive() {
+                return false;
+            }
+        }
+        self.automaton.on_exit()
+    }
+
+    // Same as validate() for UTF-8 text read from r, e.g. a file, without loading it all in memory
+    // A char split across two reads is decoded once complete, invalid UTF-8 gives an InvalidData error
+    // Stops reading as soon as no match is possible anymore
+    pub fn validate_reader<R: std::io::Read>(&mut self, r: R) -> std::io::Result<bool> {
+        let mut chars = ReaderChars { reader: r, pending: Vec::new(), decoded: Vec::new().into_iter(), error: None, done: false };
+        let matched = self.stream_validate(&mut chars);
+        match chars.error {
+            Some(e) => Err(e),
+            None => Ok(matched),
+        }
+    }
+
+    // Reads one more char of the string being validated, the first call starting a new string
+    // The other scanning methods (is_match(), find()...) share the read state and must not be called before finish()
+    pub fn feed(&mut self, c: char) {
+        if !self.streaming {
+            self.start_stream();
+        }
+        self.automaton.read(&c);
+    }
+
+    // Ends the string given to feed() and returns whether it is in the language, as validate() would
+    // The next feed() starts a new string
+    pub fn finish(&mut self) -> bool {
+        if !self.streaming {
+            self.start_stream();
+        }
+        self.streaming = false;
+        self.automaton.on_exit()
+    }
+
+    // A malformed automaton leaves no state to read from, so the stream matches nothing
+    fn start_stream(&mut self) {
+        if self.automaton.init_for_read().is_ok() {
             self.automaton.read_empty();
         }
+        self.streaming = true;
+    }
+
+    // Scanner borrowing the compiled regex, so one Vregex can be shared (e.g. across threads)
+    // while each matcher keeps its own read position
+    pub fn matcher(&self) -> Matcher<'_> {
+        Matcher {
+            cursor: self.automaton.cursor(),
+            #[cfg(feature = "normalization")]
+            normalize: self.normalize,
+        }
+    }
+
+    // Takes s and return if some substring of s (possibly empty) is in the language defined by the regex
+    // Unlike validate(), which is anchored on both ends, this behaves like the regex surrounded by .*
+    // minus the .* on the side of a ^ or $ anchor
+    // A run is restarted from the entry states at each position, so s is read only once
+    // A malformed automaton matches nothing
+    pub fn is_match(&mut self, s: &str) -> bool {
+        let s = self.normalized(s);
+        if self.automaton.init_for_read().is_err() {
+            return false;
+        }
+        self.automaton.read_empty();
+        if self.automaton.on_exit() && !self.anchored_end {
+            return true;
+        }
         for c in s.chars() {
             self.automaton.read(&c);
+            if self.anchored_start {
+                if !self.automaton.is_alive() {
+                    return false;
+                }
+            } else {
+                self.automaton.restart();
+            }
+            if self.automaton.on_exit() && !self.anchored_end {
+                return true;
+            }
         }
         self.automaton.on_exit()
     }
 
-    fn build(&mut self, s: &str) -> Result<(), VregexError> {
-        let reg = regex_parsing::parse_regex(s)?;
-        build_automaton(&mut self.automaton, reg)?;
+    // True iff s is the beginning of some string accepted by the regex, s itself included
+    // Meant for autocompletion, where the input typed so far must still be able to lead to a match
+    // A malformed automaton accepts no prefix
+    pub fn is_prefix(&mut self, s: &str) -> bool {
+        let s = self.normalized(s);
+        if self.automaton.init_for_read().is_err() {
+            return false;
+        }
+        self.automaton.read_empty();
+        for c in s.chars() {
+            self.automaton.read(&c);
+            if !self.automaton.is_alive() {
+                return false;
+            }
+        }
+        self.automaton.can_reach_exit()
+    }
+
+    /// Byte span (start, end) of the leftmost-longest match in s
+    ///
+    /// ```
+    /// use vregex::Vregex;
+    ///
+    /// let mut vregex = Vregex::new("[0-9]{1,}").unwrap();
+    /// assert_eq!(vregex.find("abc 123 45"), Some((4, 7)));
+    /// assert_eq!(vregex.find("abc"), None);
+    /// ```
+    // An empty match gives a zero-width span, e.g. a* on "baa" gives (0, 0)
+    // With ^ the match can only start at 0 and with $ it can only end at s.len()
+    pub fn find(&mut self, s: &str) -> Option<(usize, usize)> {
+        self.find_with(s, MatchPolicy::LeftmostLongest)
+    }
+
+    // Same as find() but the end of the match is chosen by policy
+    pub fn find_with(&mut self, s: &str, policy: MatchPolicy) -> Option<(usize, usize)> {
+        self.find_at(s, 0, policy)
+    }
+
+    // Returns every non-overlapping leftmost-longest match of s, in order
+    // After a match the scan resumes at its end, after a zero-width match it resumes one char further
+    // so a* on "ab" gives (0, 1), (1, 1) and (2, 2)
+    pub fn find_iter(&mut self, s: &str) -> Vec<(usize, usize)> {
+        self.matches(s).collect()
+    }
+
+    // Same matches as find_iter(), found one at a time as the iterator is advanced,
+    // e.g. matches(s).take(2) stops reading s after the second match
+    // Only borrows the regex, so several iterators can run at once
+    pub fn matches<'r, 's>(&'r self, s: &'s str) -> Matches<'r, 's> {
+        Matches {
+            regex: self,
+            haystack: s,
+            pos: Some(0),
+        }
+    }
+
+    // Every match of s, overlapping or nested ones included: for each start in order, every end in increasing order
+    // e.g. a* on "aa" gives (0, 0), (0, 1), (0, 2), (1, 1), (1, 2) and (2, 2)
+    // Reads s again from each start, so this is quadratic in the length of s (and so is the output for .*)
+    pub fn find_overlapping(&mut self, s: &str) -> Vec<(usize, usize)> {
+        let starts: Vec<usize> = s.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(s.len()))
+            .filter(|start| !self.anchored_start || *start == 0)
+            .collect();
+        let mut matches = Vec::new();
+        for start in starts {
+            if self.automaton.init_for_read().is_err() {
+                return Vec::new();
+            }
+            self.automaton.read_empty();
+            if self.automaton.on_exit() {
+                matches.push((start, start));
+            }
+            for (i, c) in s[start..].char_indices() {
+                self.automaton.read(&c);
+                if !self.automaton.is_alive() {
+                    break;
+                }
+                if self.automaton.on_exit() {
+                    matches.push((start, start + i + c.len_utf8()));
+                }
+            }
+        }
+        matches.retain(|(_, end)| !self.anchored_end || *end == s.len());
+        matches
+    }
+
+    // Leftmost-longest match of s like find(), along with the span of every capture group inside it
+    // Index 0 is the whole match and index i the group opened by the i-th parenthesis, None if it took no part in the match
+    // Groups are tracked through the automaton states, see Automaton::captures()
+    pub fn captures(&mut self, s: &str) -> Option<Vec<Option<(usize, usize)>>> {
+        let (start, end) = self.find(s)?;
+        let input = s[start..end].char_indices().map(|(i, c)| (c, start + i + c.len_utf8()));
+        self.automaton.captures(start, input, &self.tags, self.nb_groups)
+    }
+
+    // Same match as captures(), with the span of every named group keyed by its name
+    // Named groups that took no part in the match are left out, e.g. (?<x>a)?b on "b" gives an empty map
+    pub fn captures_named(&mut self, s: &str) -> Option<HashMap<String, (usize, usize)>> {
+        let spans = self.captures(s)?;
+        let named = self.names.iter()
+            .filter_map(|(name, index)| spans.get(*index).copied().flatten().map(|span| (name.clone(), span)))
+            .collect();
+        Some(named)
+    }
+
+    // Pieces of s between the matches of find_iter(), so n matches give n + 1 pieces,
+    // empty ones included when a match is at either end of s or right after another one
+    // e.g. ab on "ab1ab" gives ["", "1", ""], and () on "ab" gives ["", "a", "b", ""]
+    pub fn split<'a>(&mut self, s: &'a str) -> Vec<&'a str> {
+        let mut pieces = Vec::new();
+        let mut last = 0;
+        for (start, end) in self.find_iter(s) {
+            pieces.push(&s[last..start]);
+            last = end;
+        }
+        pieces.push(&s[last..]);
+        pieces
+    }
+
+    // Copy of s where every match of find_iter() is replaced by replacement
+    // A zero-width match inserts replacement without removing anything
+    pub fn replace_all(&mut self, s: &str, replacement: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut last = 0;
+        for (start, end) in self.find_iter(s) {
+            out.push_str(&s[last..start]);
+            out.push_str(replacement);
+            last = end;
+        }
+        out.push_str(&s[last..]);
+        out
+    }
+
+    // Number of matches find_iter() would return, zero-width ones included
+    pub fn count_matches(&mut self, s: &str) -> usize {
+        self.find_iter(s).len()
+    }
+
+    // Same as find_with() but only considers matches starting at or after the byte index from
+    fn find_at(&self, s: &str, from: usize, policy: MatchPolicy) -> Option<(usize, usize)> {
+        if self.anchored_start {
+            return match from {
+                0 => self.match_end_at(s, 0, policy).map(|end| (0, end)),
+                _ => None,
+            };
+        }
+        let starts = s[from..].char_indices()
+            .map(|(i, _)| from + i)
+            .chain(std::iter::once(s.len()));
+        for start in starts {
+            if let Some(end) = self.match_end_at(s, start, policy) {
+                return Some((start, end));
+            }
+        }
+        None
+    }
+
+    // Reads s from the byte index start and returns the end of the last exit state hit,
+    // or of the first one for LeftmostShortest, which has to be the end of s when the regex ends with $
+    fn match_end_at(&self, s: &str, start: usize, policy: MatchPolicy) -> Option<usize> {
+        let mut cursor = self.automaton.cursor();
+        if cursor.init_for_read().is_err() {
+            return None;
+        }
+        // With $ only the end of s counts, so the first exit state hit is not necessarily a match
+        let stop_at_first = policy == MatchPolicy::LeftmostShortest && !self.anchored_end;
+        cursor.read_empty();
+        let mut end = None;
+        if cursor.on_exit() {
+            end = Some(start);
+        }
+        for (i, c) in s[start..].char_indices() {
+            if end.is_some() && stop_at_first {
+                break;
+            }
+            if !cursor.read_checked(&c) {
+                break;
+            }
+            if cursor.on_exit() {
+                end = Some(start + i + c.len_utf8());
+            }
+        }
+        end.filter(|end| !self.anchored_end || *end == s.len())
+    }
+
+    // Regex accepting the strings accepted by both self and other
+    // Capture groups are lost, captures() only gives the whole match
+    pub fn intersect(&self, other: &Vregex) -> Vregex {
+        Vregex {
+            automaton: self.automaton.intersect(&other.automaton),
+            universe: self.universe.union(&other.universe).copied().collect(),
+            tags: HashMap::new(),
+            nb_groups: 0,
+            names: HashMap::new(),
+            // A string matching both is anchored as soon as one of them is
+            anchored_start: self.anchored_start || other.anchored_start,
+            anchored_end: self.anchored_end || other.anchored_end,
+            streaming: false,
+            #[cfg(feature = "normalization")]
+            normalize: self.normalize,
+        }
+    }
+
+    // Regex accepting the strings accepted by self but not by other
+    // Both universes make up the alphabet of the complement of other, so no string of self is lost
+    // Capture groups are lost like with intersect(), while the anchors of self are kept
+    pub fn difference(&self, other: &Vregex) -> Vregex {
+        let universe: HashSet<char> = self.universe.union(&other.universe).copied().collect();
+        Vregex {
+            automaton: self.automaton.difference(&other.automaton, &universe),
+            universe,
+            tags: HashMap::new(),
+            nb_groups: 0,
+            names: HashMap::new(),
+            anchored_start: self.anchored_start,
+            anchored_end: self.anchored_end,
+            streaming: false,
+            #[cfg(feature = "normalization")]
+            normalize: self.normalize,
+        }
+    }
+
+    // Sample of the accepted strings with at most max_len chars, shortest first
+    // Stops after MAX_ENUMERATED strings since e.g. .* accepts a lot of them even for a small max_len
+    pub fn enumerate(&self, max_len: usize) -> impl Iterator<Item = String> {
+        self.automaton.words(max_len)
+            .map(|word| word.into_iter().collect())
+            .take(MAX_ENUMERATED)
+    }
+
+    // One of the shortest strings accepted, e.g. "b" for a*b, or None if no string is accepted
+    pub fn shortest_match(&self) -> Option<String> {
+        self.automaton.shortest_word().map(|word| word.into_iter().collect())
+    }
+
+    // Number of distinct strings of exactly len chars accepted, saturating at u64::MAX
+    pub fn count_words(&self, len: usize) -> u64 {
+        self.automaton.count_words(len)
+    }
+
+    // True iff some string is accepted by both regexes, cheaper than checking that intersect() matches something
+    pub fn overlaps(&self, other: &Vregex) -> bool {
+        self.automaton.overlaps(&other.automaton)
+    }
+
+    // True iff every string accepted by self is accepted by other, i.e. their difference() matches nothing
+    // e.g. ab is a subset of a. while a. is not a subset of ab
+    pub fn is_subset_of(&self, other: &Vregex) -> bool {
+        self.difference(other).matches_nothing()
+    }
+
+    // True iff no string at all is accepted, e.g. for [^a-zA-Z0-9] over the default universe
+    pub fn matches_nothing(&self) -> bool {
+        self.automaton.is_empty_language()
+    }
+
+    // True iff both regexes define the same language
+    pub fn equivalent(&self, other: &Vregex) -> bool {
+        self.automaton.equivalent(&other.automaton)
+    }
+
+    // Without dot_matches_newline, . matches the universe except '\n'
+    fn build_ast(&mut self, reg: Regex, state_limit: Option<usize>, dot_matches_newline: bool) -> Result<(), VregexError> {
+        if let Some(name) = reg.duplicate_group_name() {
+            return Err(VregexError::InvalidAst { message: format!("duplicate group name '{}'", name) });
+        }
+        self.universe.extend(reg.symbols());
+        self.nb_groups = reg.max_group();
+        self.names = reg.group_names().into_iter().map(|(name, index)| (name.to_string(), index)).collect();
+        let (anchored_start, body, anchored_end) = reg.split_anchors();
+        self.anchored_start = anchored_start;
+        self.anchored_end = anchored_end;
+        let body = body.cloned().unwrap_or(Regex::Epsilon);
+        let mut ctx = BuildContext {
+            universe: &self.universe,
+            tags: HashMap::new(),
+            state_limit,
+            dot_matches_newline,
+        };
+        build_automaton(&mut self.automaton, body, &mut ctx)?;
+        self.tags = ctx.tags;
+        self.automaton.finalize();
         Ok(())
     }
 }
 
-fn build_automaton(autom: &mut automaton::Automaton<char>, reg: regex_parsing::Regex) -> Result<(), VregexError> {
+// The empty regex, only accepting the empty string
+impl Default for Vregex {
+    fn default() -> Self {
+        let mut out = Vregex::empty(default_universe());
+        // Epsilon has no group, repetition or anchor to reject, so this cannot fail
+        let _ = out.build_ast(Regex::Epsilon, None, false);
+        out
+    }
+}
+
+impl std::str::FromStr for Vregex {
+    type Err = VregexError;
+
+    fn from_str(regex: &str) -> Result<Self, Self::Err> {
+        Vregex::new(regex)
+    }
+}
+
+// Lazy iterator over the matches of a Vregex in a string, see Vregex::matches()
+#[derive(Debug, Clone)]
+pub struct Matches<'r, 's> {
+    regex: &'r Vregex,
+    haystack: &'s str,
+    // Byte index the next search starts at, None once the end of haystack was passed
+    pos: Option<usize>,
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = (usize, usize);
+
+    // After a match the search resumes at its end, after a zero-width match one char further
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let pos = self.pos?;
+        let found = self.regex.find_at(self.haystack, pos, MatchPolicy::LeftmostLongest);
+        self.pos = match found {
+            Some((start, end)) if end > start => Some(end),
+            Some((_, end)) => self.haystack[end..].chars().next().map(|c| end + c.len_utf8()),
+            None => None,
+        };
+        found
+    }
+}
+
+// Read position over a compiled Vregex, see Vregex::matcher()
+#[derive(Debug)]
+pub struct Matcher<'a> {
+    cursor: automaton::Cursor<'a, char>,
+    #[cfg(feature = "normalization")]
+    normalize: Option<Normalization>,
+}
+
+impl Matcher<'_> {
+    // Same as Vregex::validate()
+    pub fn validate(&mut self, s: &str) -> Result<bool, VregexError> {
+        #[cfg(feature = "normalization")]
+        let normalized = match self.normalize {
+            Some(form) => normalize(s, form),
+            None => Cow::Borrowed(s),
+        };
+        #[cfg(feature = "normalization")]
+        let s = normalized.as_ref();
+        self.cursor.init_for_read()?;
+        if s.is_empty() {
+            self.cursor.read_empty();
+        }
+        for c in s.chars() {
+            if !self.cursor.read_checked(&c) {
+                return Ok(false);
+            }
+        }
+        Ok(self.cursor.on_exit())
+    }
+}
+
+#[cfg(feature = "normalization")]
+fn normalize(s: &str, form: Normalization) -> Cow<'_, str> {
+    match form {
+        Normalization::Nfc => Cow::Owned(unicode_normalization::UnicodeNormalization::nfc(s).collect()),
+    }
+}
+
+// UTF-8 text of a reader decoded into chars, see Vregex::validate_reader()
+// Reads a chunk only once the chars of the previous one are used up, and ends at the end of the reader
+// or at the first error, kept in error
+struct ReaderChars<R> {
+    reader: R,
+    // Bytes read but not decoded yet, at most the start of one char between two reads
+    pending: Vec<u8>,
+    decoded: std::vec::IntoIter<char>,
+    error: Option<std::io::Error>,
+    done: bool,
+}
+
+impl<R: std::io::Read> Iterator for ReaderChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+        let mut buf = [0; 8192];
+        loop {
+            if let Some(c) = self.decoded.next() {
+                return Some(c);
+            }
+            if self.done {
+                return None;
+            }
+            let n = match self.reader.read(&mut buf) {
+                Ok(0) => {
+                    self.done = true;
+                    if !self.pending.is_empty() {
+                        self.error = Some(invalid());
+                    }
+                    continue;
+                }
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    self.error = Some(e);
+                    continue;
+                }
+            };
+            self.pending.extend_from_slice(&buf[..n]);
+            let valid = match std::str::from_utf8(&self.pending) {
+                Ok(text) => text.len(),
+                // Only the end is cut, the rest comes with the next read
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => {
+                    self.done = true;
+                    self.error = Some(invalid());
+                    continue;
+                }
+            };
+            // The first valid bytes were just checked, so this cannot fail
+            let text = std::str::from_utf8(&self.pending[..valid]).unwrap_or_default();
+            self.decoded = text.chars().collect::<Vec<char>>().into_iter();
+            self.pending.drain(..valid);
+        }
+    }
+}
+
+// Symbol type an automaton can be built for, the parser always producing chars
+trait Symbol: Eq + std::hash::Hash + fmt::Debug + Copy {
+    fn from_char(c: char) -> Self;
+}
+
+impl Symbol for char {
+    fn from_char(c: char) -> Self {
+        c
+    }
+}
+
+// The parser only yields ASCII literals and \xHH escapes, all of them below 0x100
+impl Symbol for u8 {
+    fn from_char(c: char) -> Self {
+        c as u8
+    }
+}
+
+// What building needs besides the automaton and the regex
+struct BuildContext<'a, T: Symbol> {
+    universe: &'a HashSet<T>,
+    // Filled with the states bounding each capture group
+    tags: HashMap<usize, automaton::GroupTag>,
+    state_limit: Option<usize>,
+    // When off . never matches '\n', even if it is in the universe
+    dot_matches_newline: bool,
+}
+
+// Fails with StateLimitExceeded as soon as a sub-automaton takes the state count past ctx.state_limit
+fn build_automaton<T: Symbol>(autom: &mut automaton::Automaton<T>, reg: regex_parsing::Regex, ctx: &mut BuildContext<T>) -> Result<(), VregexError> {
+    build_node(autom, reg, ctx)?;
+    match ctx.state_limit {
+        Some(limit) if autom.nb_state() > limit => Err(VregexError::StateLimitExceeded { limit }),
+        _ => Ok(()),
+    }
+}
+
+// Builds the root of reg, its children going through build_automaton()
+fn build_node<T: Symbol>(autom: &mut automaton::Automaton<T>, reg: regex_parsing::Regex, ctx: &mut BuildContext<T>) -> Result<(), VregexError> {
     use crate::vregex::regex_parsing::Regex::*;
     match reg {
         Literal(char) => {
             build_literal(autom, char)?;
             Ok(())
         }
+        Class(ranges) => {
+            build_class(autom, &ranges)?;
+            Ok(())
+        }
+        NegatedClass(ranges) => {
+            build_negated_class(autom, &ranges, ctx.universe)?;
+            Ok(())
+        }
+        AnyChar => {
+            build_any_char(autom, ctx.universe, ctx.dot_matches_newline)?;
+            Ok(())
+        }
+        Epsilon => {
+            build_epsilon(autom)?;
+            Ok(())
+        }
+        Group(index, ex) | NamedGroup(_, index, ex) => {
+            build_automaton(autom, *ex, ctx)?;
+            build_group(autom, index, &mut ctx.tags)?;
+            Ok(())
+        }
         Concat(left_ex, right_ex) => {
-            build_automaton(autom, *left_ex)?;
-            let old_exits = autom.get_exit();
-            let old_entries = autom.get_entry();
-            autom.reset_entry();
-            autom.reset_exit();
-            build_automaton(autom, *right_ex)?;
-            build_concat(autom, &old_entries, &old_exits)?;
+            // Long concatenations (like unrolled repetitions) nest on the left, so they are built in a loop rather than recursively
+            let mut parts = vec![*right_ex];
+            let mut first = *left_ex;
+            while let Concat(left, right) = first {
+                parts.push(*right);
+                first = *left;
+            }
+            build_automaton(autom, first, ctx)?;
+            for part in parts.into_iter().rev() {
+                let old_exits = autom.get_exit();
+                let old_entries = autom.get_entry();
+                autom.reset_entry();
+                autom.reset_exit();
+                build_automaton(autom, part, ctx)?;
+                build_concat(autom, &old_entries, &old_exits)?;
+            }
             Ok(())
         },
         Plus(left_ex, right_ex) => {
-            build_automaton(autom, *left_ex)?;
-            build_automaton(autom, *right_ex)?;
+            // a+b+c nests on the left, so the alternatives are gathered first and share a single new entry
+            // rather than getting one per +, which would chain epsilon transitions as deep as the alternation is long
+            let mut alternatives = Vec::new();
+            let mut todo = vec![*right_ex, *left_ex];
+            while let Some(ex) = todo.pop() {
+                match ex {
+                    Plus(left, right) => {
+                        todo.push(*right);
+                        todo.push(*left);
+                    }
+                    ex => alternatives.push(ex),
+                }
+            }
+            let mut entries = Vec::new();
+            let mut exits = Vec::new();
+            for alternative in alternatives {
+                build_automaton(autom, alternative, ctx)?;
+                entries.extend(autom.get_entry());
+                exits.extend(autom.get_exit());
+                autom.reset_entry();
+                autom.reset_exit();
+            }
+            for st in entries {
+                autom.add_entry(st)?;
+            }
+            for st in exits {
+                autom.add_exit(st)?;
+            }
             build_plus(autom)?;
             Ok(())
         },
         Star(ex) => {
-            build_automaton(autom, *ex)?;
+            build_automaton(autom, *ex, ctx)?;
             build_star(autom)?;
             Ok(())
         },
+        Opt(ex) => {
+            build_automaton(autom, *ex, ctx)?;
+            build_opt(autom)?;
+            Ok(())
+        },
+        Plus1(ex) => {
+            let repeated = Star(ex.clone());
+            build_automaton(autom, Concat(ex, Box::new(repeated)), ctx)?;
+            Ok(())
+        },
+        // Anchors at the start and at the end were taken off by the caller, see Regex::split_anchors()
+        StartAnchor | EndAnchor => {
+            Err(VregexError::InvalidAst { message: "^ and $ are only allowed at the start and at the end of the regex".to_string() })
+        },
+        Repeat { inner, min, max } => {
+            if let Some(max) = max.filter(|max| *max < min) {
+                return Err(VregexError::InvalidAst { message: format!("repetition {{{},{}}} has min > max", min, max) });
+            }
+            // The unrolled copies are checked against the limit before being allocated, since a{4000000000} alone
+            // would exhaust memory: each copy takes two states at least, one for the empty regex
+            if let Some(limit) = ctx.state_limit {
+                let copies = max.unwrap_or(min.saturating_add(1));
+                let per_copy = if matches!(*inner, Epsilon) { 1 } else { 2 };
+                if copies.saturating_mul(per_copy) > limit {
+                    return Err(VregexError::StateLimitExceeded { limit });
+                }
+            }
+            build_automaton(autom, expand_repeat(*inner, min, max), ctx)?;
+            Ok(())
+        },
+    }
+}
+
+// Unrolls inner{min,max} into min copies of inner followed by either a star or (max - min) optional copies
+// Gives Epsilon when the repetition can only match the empty string
+fn expand_repeat(inner: regex_parsing::Regex, min: usize, max: Option<usize>) -> regex_parsing::Regex {
+    use crate::vregex::regex_parsing::Regex::*;
+    let mut parts = vec![inner.clone(); min];
+    match max {
+        None => parts.push(Star(Box::new(inner))),
+        Some(max) => parts.extend(vec![Opt(Box::new(inner)); max - min]),
     }
+    parts.into_iter()
+        .reduce(|left, right| Concat(Box::new(left), Box::new(right)))
+        .unwrap_or(Epsilon)
 }
 
-fn build_literal(autom: &mut automaton::Automaton<char>, c: char) -> Result<(), VregexError> {
+fn build_literal<T: Symbol>(autom: &mut automaton::Automaton<T>, c: char) -> Result<(), VregexError> {
     let nb_added = 2;
     let state_added: Vec<usize> = autom.add_n_state(nb_added);
     autom.add_entry(state_added[0])?;
     autom.add_exit(state_added[1])?;
-    autom.add_transition(state_added[0], state_added[1], c)?;
+    autom.add_transition(state_added[0], state_added[1], T::from_char(c))?;
+    label_bounds(autom, state_added[0], state_added[1], &format!("literal {:?}", c))?;
     Ok(())
 }
 
-fn build_concat(autom: &mut automaton::Automaton<char>, old_entries: &HashSet<usize>, old_exit: &HashSet<usize>) -> Result<(), VregexError> {
-    for o_exit_r in old_exit.iter() {
-        for n_entries_r in autom.get_entry().iter() {
-            autom.add_eps_transition(*o_exit_r, *n_entries_r)?;
+fn build_class<T: Symbol>(autom: &mut automaton::Automaton<T>, ranges: &[(char, char)]) -> Result<(), VregexError> {
+    let nb_added = 2;
+    let state_added: Vec<usize> = autom.add_n_state(nb_added);
+    autom.add_entry(state_added[0])?;
+    autom.add_exit(state_added[1])?;
+    for (low, high) in ranges.iter() {
+        for c in *low..=*high {
+            autom.add_transition(state_added[0], state_added[1], T::from_char(c))?;
         }
     }
-    autom.reset_entry();
-    for o_entry_r in old_entries.iter() {
-        autom.add_entry(*o_entry_r)?;
-    }
+    label_bounds(autom, state_added[0], state_added[1], "class")?;
     Ok(())
 }
 
-fn build_plus(autom: &mut automaton::Automaton<char>) -> Result<(), VregexError> {
+fn build_negated_class<T: Symbol>(autom: &mut automaton::Automaton<T>, ranges: &[(char, char)], universe: &HashSet<T>) -> Result<(), VregexError> {
+    let nb_added = 2;
+    let state_added: Vec<usize> = autom.add_n_state(nb_added);
+    autom.add_entry(state_added[0])?;
+    autom.add_exit(state_added[1])?;
+    let excluded: HashSet<T> = ranges.iter()
+        .flat_map(|(low, high)| *low..=*high)
+        .map(T::from_char)
+        .collect();
+    for c in universe.difference(&excluded) {
+        autom.add_transition(state_added[0], state_added[1], *c)?;
+    }
+    label_bounds(autom, state_added[0], state_added[1], "negated class")?;
+    Ok(())
+}
+
+// Same as a negated class excluding nothing
+fn build_any_char<T: Symbol>(autom: &mut automaton::Automaton<T>, universe: &HashSet<T>, dot_matches_newline: bool) -> Result<(), VregexError> {
+    if dot_matches_newline {
+        build_negated_class(autom, &[], universe)
+    } else {
+        build_negated_class(autom, &[('\n', '\n')], universe)
+    }
+}
+
+fn build_epsilon<T: Symbol>(autom: &mut automaton::Automaton<T>) -> Result<(), VregexError> {
+    let state = autom.add_state();
+    autom.add_entry(state)?;
+    autom.add_exit(state)?;
+    autom.set_label(state, "epsilon".to_string())?;
+    Ok(())
+}
+
+// Wraps the current sub-automaton between a new entry and a new exit tagged as the bounds of group index
+fn build_group<T: Symbol>(autom: &mut automaton::Automaton<T>, index: usize, tags: &mut HashMap<usize, automaton::GroupTag>) -> Result<(), VregexError> {
+    let old_entries = autom.get_entry();
+    let old_exits = autom.get_exit();
+    autom.reset_entry();
+    autom.reset_exit();
+    let open = autom.add_state();
+    let close = autom.add_state();
+    autom.add_entry(open)?;
+    autom.add_exit(close)?;
+    for o_entry_r in old_entries.iter() {
+        autom.add_eps_transition(open, *o_entry_r)?;
+    }
+    for o_exit_r in old_exits.iter() {
+        autom.add_eps_transition(*o_exit_r, close)?;
+    }
+    tags.insert(open, automaton::GroupTag::Open(index));
+    tags.insert(close, automaton::GroupTag::Close(index));
+    label_bounds(autom, open, close, &format!("group {}", index))?;
+    Ok(())
+}
+
+fn build_concat<T: Symbol>(autom: &mut automaton::Automaton<T>, old_entries: &HashSet<usize>, old_exit: &HashSet<usize>) -> Result<(), VregexError> {
+    let new_entries: Vec<usize> = autom.entry_iter().collect();
+    for o_exit_r in old_exit.iter() {
+        for n_entries_r in new_entries.iter() {
+            autom.add_eps_transition(*o_exit_r, *n_entries_r)?;
+        }
+    }
+    autom.reset_entry();
+    for o_entry_r in old_entries.iter() {
+        autom.add_entry(*o_entry_r)?;
+    }
+    Ok(())
+}
+
+fn build_plus<T: Symbol>(autom: &mut automaton::Automaton<T>) -> Result<(), VregexError> {
     let old_entries = autom.get_entry();
     autom.reset_entry();
     let new_entry = autom.add_state();
@@ -114,10 +1185,37 @@ fn build_plus(autom: &mut automaton::Automaton<char>) -> Result<(), VregexError>
     for st_r in old_entries.iter() {
         autom.add_eps_transition(new_entry, *st_r)?;
     }
+    autom.set_label(new_entry, "alternation entry".to_string())?;
+    Ok(())
+}
+
+fn build_star<T: Symbol>(autom: &mut automaton::Automaton<T>) -> Result<(), VregexError> {
+    let old_entries = autom.get_entry();
+    let old_exits = autom.get_exit();
+    autom.reset_entry();
+    autom.reset_exit();
+    let new_entry = autom.add_state();
+    let new_exit = autom.add_state();
+    autom.add_entry(new_entry)?;
+    autom.add_exit(new_exit)?;
+    // Skip the sub-automaton, enter it, leave it, or loop back from its end to its start
+    autom.add_eps_transition(new_entry, new_exit)?;
+    for o_entry_r in old_entries.iter() {
+        autom.add_eps_transition(new_entry, *o_entry_r)?;
+    }
+    for o_exit_r in old_exits.iter() {
+        autom.add_eps_transition(*o_exit_r, new_exit)?;
+        for o_entry_r in old_entries.iter() {
+            autom.add_eps_transition(*o_exit_r, *o_entry_r)?;
+        }
+    }
+    label_bounds(autom, new_entry, new_exit, "star")?;
     Ok(())
 }
 
-fn build_star(autom: &mut automaton::Automaton<char>) -> Result<(), VregexError> {
+// The skip goes between a new entry and a new exit so that it never walks through the bounds of the operand:
+// linking its own entries to its exits would let a loop inside it reach the exit early, e.g. (a*b)? accepting a
+fn build_opt<T: Symbol>(autom: &mut automaton::Automaton<T>) -> Result<(), VregexError> {
     let old_entries = autom.get_entry();
     let old_exits = autom.get_exit();
     autom.reset_entry();
@@ -127,13 +1225,20 @@ fn build_star(autom: &mut automaton::Automaton<char>) -> Result<(), VregexError>
     autom.add_entry(new_entry)?;
     autom.add_exit(new_exit)?;
     autom.add_eps_transition(new_entry, new_exit)?;
-    autom.add_eps_transition(new_exit, new_entry)?;
     for o_entry_r in old_entries.iter() {
         autom.add_eps_transition(new_entry, *o_entry_r)?;
     }
     for o_exit_r in old_exits.iter() {
         autom.add_eps_transition(*o_exit_r, new_exit)?;
     }
+    label_bounds(autom, new_entry, new_exit, "optional")?;
+    Ok(())
+}
+
+// Labels entry and exit as the bounds of what they were built for, see Automaton::label()
+fn label_bounds<T: Symbol>(autom: &mut automaton::Automaton<T>, entry: usize, exit: usize, origin: &str) -> Result<(), VregexError> {
+    autom.set_label(entry, format!("{} entry", origin))?;
+    autom.set_label(exit, format!("{} exit", origin))?;
     Ok(())
 }
 
@@ -143,15 +1248,63 @@ mod tests {
 
     #[test]
     fn test_validate_literal() {
-        let mut vrg = Vregex::new("a");
+        let mut vrg = Vregex::new("a").unwrap();
         assert!(vrg.validate("a").unwrap());
         assert!(!vrg.validate("b").unwrap());
         assert!(!vrg.validate("").unwrap());
     }
 
+    #[test]
+    fn test_validate_dead_scan() {
+        let mut vrg = Vregex::new("abc").unwrap();
+        assert!(!vrg.validate("abX").unwrap());
+        assert!(!vrg.validate("abXabc").unwrap());
+        assert!(vrg.validate("abc").unwrap());
+        let mut matcher = vrg.matcher();
+        assert!(!matcher.validate("Xabc").unwrap());
+        assert!(matcher.validate("abc").unwrap());
+    }
+
+    #[test]
+    fn test_validate_digits_uppercase() {
+        let mut vrg = Vregex::new("A1b").unwrap();
+        assert!(vrg.validate("A1b").unwrap());
+        assert!(!vrg.validate("a1b").unwrap());
+        assert!(!vrg.validate("A1B").unwrap());
+
+        let mut vrg = Vregex::new("[A-Z][0-9]*").unwrap();
+        assert!(vrg.validate("X42").unwrap());
+        assert!(!vrg.validate("x42").unwrap());
+
+        let mut vrg = Vregex::new("[^0-9]").unwrap();
+        assert!(vrg.validate("Q").unwrap());
+        assert!(!vrg.validate("7").unwrap());
+    }
+
+    #[test]
+    fn test_validate_escape() {
+        let mut vrg = Vregex::new("a\\*b").unwrap();
+        assert!(vrg.validate("a*b").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+        assert!(!vrg.validate("aab").unwrap());
+
+        let mut vrg = Vregex::new("\\(\\+\\)\\\\").unwrap();
+        assert!(vrg.validate("(+)\\").unwrap());
+
+        let mut vrg = Vregex::new("[\\-\\]a]*").unwrap();
+        assert!(vrg.validate("-]a").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+
+        let mut vrg = Vregex::new("\\x41\\x20b").unwrap();
+        assert!(vrg.validate("A b").unwrap());
+
+        assert!(matches!(Vregex::new("a\\"), Err(VregexError::ParsingError { offset: 2, .. })));
+        assert!(matches!(Vregex::new("a\\q"), Err(VregexError::ParsingError { offset: 2, .. })));
+    }
+
     #[test]
     fn test_validate_plus() {
-        let mut vrg = Vregex::new("a+b");
+        let mut vrg = Vregex::new("a+b").unwrap();
         assert!(vrg.validate("a").unwrap());
         assert!(vrg.validate("b").unwrap());
         assert!(!vrg.validate("c").unwrap());
@@ -159,7 +1312,7 @@ mod tests {
 
     #[test]
     fn test_validate_concat() {
-        let mut vrg = Vregex::new("ab");
+        let mut vrg = Vregex::new("ab").unwrap();
         assert!(vrg.validate("ab").unwrap());
         assert!(!vrg.validate("b").unwrap());
         assert!(!vrg.validate("a").unwrap());
@@ -169,7 +1322,7 @@ mod tests {
 
     #[test]
     fn test_validate_star() {
-        let mut vrg = Vregex::new("a*");
+        let mut vrg = Vregex::new("a*").unwrap();
         assert!(vrg.validate("a").unwrap());
         assert!(vrg.validate("aaaaaaaaaaa").unwrap());
         assert!(vrg.validate("").unwrap());
@@ -177,9 +1330,919 @@ mod tests {
         assert!(!vrg.validate("c").unwrap());
     }
 
+    #[test]
+    fn test_validate_pipe() {
+        let mut vrg = Vregex::new("a|b").unwrap();
+        assert!(vrg.validate("a").unwrap());
+        assert!(vrg.validate("b").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("a+b").unwrap()));
+
+        let mut vrg = Vregex::new("(a|b)c").unwrap();
+        assert!(vrg.validate("ac").unwrap());
+        assert!(vrg.validate("bc").unwrap());
+        assert!(!vrg.validate("c").unwrap());
+        assert!(!vrg.validate("abc").unwrap());
+
+        let mut vrg = Vregex::new("a|b+c").unwrap();
+        assert!(vrg.validate("c").unwrap());
+    }
+
+    #[test]
+    fn test_validate_many_alternatives() {
+        let words: Vec<String> = ('a'..='l').map(|c| c.to_string()).collect();
+        let mut vrg = Vregex::new(&words.join("+")).unwrap();
+        for word in words.iter() {
+            assert!(vrg.validate(word).unwrap(), "{}", word);
+        }
+        assert!(!vrg.validate("m").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        // Grouping the same alternatives differently gives the same language
+        assert!(vrg.equivalent(&Vregex::new("a+(b+(c+d))+(e+f+g)+h+i+j+k+l").unwrap()));
+
+        // One epsilon transition per alternative, literals having none
+        let nb_eps = |vrg: &Vregex| -> usize {
+            (0..vrg.automaton.nb_state()).map(|st| vrg.automaton.outgoing(st).unwrap().1.len()).sum()
+        };
+        assert_eq!(nb_eps(&vrg), 12);
+        let words: Vec<String> = ('a'..='z').flat_map(|c| ('0'..='9').map(move |d| format!("{}{}", c, d))).collect();
+        let mut vrg = Vregex::new(&words.join("|")).unwrap();
+        // Plus the one joining the two literals of each word
+        assert_eq!(nb_eps(&vrg), 2 * words.len());
+        assert!(vrg.validate("q7").unwrap());
+        assert!(!vrg.validate("7q").unwrap());
+    }
+
+    #[test]
+    fn test_validate_plus_with_concat() {
+        let mut vrg = Vregex::new("a+bc").unwrap();
+        assert!(vrg.validate("a").unwrap());
+        assert!(vrg.validate("bc").unwrap());
+        assert!(!vrg.validate("ac").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+    }
+
+    #[test]
+    fn test_validate_opt() {
+        let mut vrg = Vregex::new("ab?c").unwrap();
+        assert!(vrg.validate("ac").unwrap());
+        assert!(vrg.validate("abc").unwrap());
+        assert!(!vrg.validate("abbc").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        let mut vrg = Vregex::new("a*?b").unwrap();
+        assert!(vrg.validate("b").unwrap());
+        assert!(vrg.validate("aab").unwrap());
+        let mut vrg = Vregex::new("(a*b)?").unwrap();
+        assert!(vrg.validate("").unwrap());
+        assert!(vrg.validate("aab").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(!vrg.validate("aa").unwrap());
+    }
+
+    #[test]
+    fn test_validate_repeat() {
+        let mut vrg = Vregex::new("a{2,3}").unwrap();
+        assert!(vrg.validate("aa").unwrap());
+        assert!(vrg.validate("aaa").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(!vrg.validate("aaaa").unwrap());
+
+        let mut vrg = Vregex::new("(ab){2}").unwrap();
+        assert!(vrg.validate("abab").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+        assert!(!vrg.validate("ababab").unwrap());
+
+        let mut vrg = Vregex::new("a{2,}b").unwrap();
+        assert!(vrg.validate("aab").unwrap());
+        assert!(vrg.validate("aaaaaab").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+
+        let mut vrg = Vregex::new("ba{0}c").unwrap();
+        assert!(vrg.validate("bc").unwrap());
+        assert!(!vrg.validate("bac").unwrap());
+    }
+
+    #[test]
+    fn test_validate_plus1() {
+        let mut vrg = Vregex::new("a{1,}").unwrap();
+        assert!(vrg.validate("a").unwrap());
+        assert!(vrg.validate("aaa").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+
+        let mut vrg = Vregex::new("(ab){1,}c").unwrap();
+        assert!(vrg.validate("abc").unwrap());
+        assert!(vrg.validate("ababc").unwrap());
+        assert!(!vrg.validate("c").unwrap());
+        assert!(!vrg.validate("abac").unwrap());
+    }
+
+    #[test]
+    fn test_repeat_malformed_bounds() {
+        assert!(matches!(Vregex::new("a{3,1}"), Err(VregexError::ParsingError { .. })));
+        assert!(matches!(Vregex::new("ba{3,1}"), Err(VregexError::ParsingError { .. })));
+        assert!(matches!(Vregex::new("a{,2}"), Err(VregexError::ParsingError { .. })));
+    }
+
+    #[test]
+    fn test_validate_class() {
+        let mut vrg = Vregex::new("[a-c]x").unwrap();
+        assert!(vrg.validate("ax").unwrap());
+        assert!(vrg.validate("bx").unwrap());
+        assert!(vrg.validate("cx").unwrap());
+        assert!(!vrg.validate("dx").unwrap());
+        assert!(!vrg.validate("x").unwrap());
+
+        let mut vrg = Vregex::new("[a-cxy]*").unwrap();
+        assert!(vrg.validate("abyxc").unwrap());
+        assert!(!vrg.validate("abz").unwrap());
+    }
+
+    #[test]
+    fn test_class_malformed() {
+        assert!(matches!(Vregex::new("[]"), Err(VregexError::ParsingError { .. })));
+        assert!(matches!(Vregex::new("[z-a]"), Err(VregexError::ParsingError { .. })));
+        assert!(matches!(Vregex::new("b[z-a]"), Err(VregexError::ParsingError { .. })));
+    }
+
+    #[test]
+    fn test_validate_negated_class() {
+        let mut vrg = Vregex::new("[^a]").unwrap();
+        assert!(!vrg.validate("a").unwrap());
+        assert!(vrg.validate("b").unwrap());
+        assert!(vrg.validate("z").unwrap());
+        assert!(!vrg.validate("").unwrap());
+
+        let mut vrg = Vregex::new("[^a-cx]*").unwrap();
+        assert!(vrg.validate("dez").unwrap());
+        assert!(!vrg.validate("dxz").unwrap());
+    }
+
+    #[test]
+    fn test_negated_class_universe() {
+        let mut vrg = Vregex::with_universe("[^a]b", ['a', 'c']).unwrap();
+        assert!(vrg.universe().contains(&'b'));
+        assert!(vrg.validate("cb").unwrap());
+        assert!(vrg.validate("bb").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+        assert!(!vrg.validate("db").unwrap());
+    }
+
+    #[test]
+    fn test_validate_any_char() {
+        let mut vrg = Vregex::new("a.c").unwrap();
+        assert!(vrg.validate("abc").unwrap());
+        assert!(vrg.validate("axc").unwrap());
+        assert!(vrg.validate("aac").unwrap());
+        assert!(!vrg.validate("ac").unwrap());
+        assert!(!vrg.validate("abbc").unwrap());
+
+        let mut vrg = Vregex::with_universe(".*", []).unwrap();
+        assert!(vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+    }
+
+    #[test]
+    fn test_is_prefix() {
+        let mut vrg = Vregex::new("abc").unwrap();
+        assert!(vrg.is_prefix(""));
+        assert!(vrg.is_prefix("ab"));
+        assert!(vrg.is_prefix("abc"));
+        assert!(!vrg.is_prefix("ax"));
+        assert!(!vrg.is_prefix("abcd"));
+
+        let mut vrg = Vregex::new("(ab)*").unwrap();
+        assert!(vrg.is_prefix("aba"));
+        assert!(!vrg.is_prefix("abb"));
+
+        // Live states that can never reach an exit do not count
+        let mut vrg = Vregex::new("a[^a-zA-Z0-9]+b").unwrap();
+        assert!(!vrg.is_prefix("a"));
+        assert!(vrg.is_prefix("b"));
+    }
+
+    #[test]
+    fn test_is_match() {
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert!(vrg.is_match("xxabxx"));
+        assert!(!vrg.validate("xxabxx").unwrap());
+        assert!(vrg.is_match("ab"));
+        assert!(vrg.is_match("aab"));
+        assert!(!vrg.is_match("axb"));
+        assert!(!vrg.is_match(""));
+
+        let mut vrg = Vregex::new("b*").unwrap();
+        assert!(vrg.is_match(""));
+        assert!(vrg.is_match("xyz"));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert_eq!(vrg.find("zzabz"), Some((2, 4)));
+        assert_eq!(vrg.find("ab"), Some((0, 2)));
+        assert_eq!(vrg.find("zzaz"), None);
+
+        let mut vrg = Vregex::new("a*").unwrap();
+        assert_eq!(vrg.find("baa"), Some((0, 0)));
+        assert_eq!(vrg.find("aab"), Some((0, 2)));
+        assert_eq!(vrg.find(""), Some((0, 0)));
+
+        let mut vrg = Vregex::new("b(a+c)*").unwrap();
+        assert_eq!(vrg.find("éébacab"), Some((4, 8)));
+    }
+
+    #[test]
+    fn test_anchors() {
+        let mut start = Vregex::new("^ab").unwrap();
+        assert_eq!(start.find("xabab"), None);
+        assert_eq!(start.find("abab"), Some((0, 2)));
+        assert_eq!(start.find_iter("abab"), vec![(0, 2)]);
+        assert!(!start.is_match("xab"));
+        assert!(start.is_match("abx"));
+
+        let mut end = Vregex::new("ab$").unwrap();
+        assert_eq!(end.find("abxab"), Some((3, 5)));
+        assert_eq!(end.find("abx"), None);
+        assert!(end.is_match("xab"));
+        assert!(!end.is_match("abx"));
+
+        let mut both = Vregex::new("^a*$").unwrap();
+        assert!(both.is_match("aaa"));
+        assert!(both.is_match(""));
+        assert!(!both.is_match("aab"));
+        assert!(!both.is_match("baa"));
+        assert_eq!(both.find("aa"), Some((0, 2)));
+        assert!(both.validate("aa").unwrap());
+
+        // Anchors apply to the whole alternation
+        let mut alt = Vregex::new("^a+b").unwrap();
+        assert!(!alt.is_match("xb"));
+
+        let misplaced = Regex::Concat(Box::new(Regex::Literal('a')), Box::new(Regex::Concat(Box::new(Regex::StartAnchor), Box::new(Regex::Literal('b')))));
+        assert!(matches!(Vregex::from_ast(misplaced), Err(VregexError::InvalidAst { .. })));
+        assert!(Vregex::new("a^b").is_err());
+    }
+
+    #[test]
+    fn test_validate_bounded() {
+        let mut vrg = Vregex::new("(a+b)*abb").unwrap();
+        let long = "ab".repeat(1000) + "b";
+        assert!(matches!(vrg.validate_bounded(&long, 100), Err(VregexError::Budget { max_steps: 100 })));
+        assert!(vrg.validate_bounded(&long, usize::MAX).unwrap());
+        assert!(!vrg.validate_bounded("ab", usize::MAX).unwrap());
+        // Nothing is spent once no state is left
+        assert!(!vrg.validate_bounded(&("c".to_string() + &long), 100).unwrap());
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut vrg = Vregex::new("ab*").unwrap();
+        let trace = vrg.trace("abb");
+        assert_eq!(trace.len(), 4);
+        assert!(trace.iter().all(|set| !set.is_empty()));
+        assert!(trace[0].iter().any(|st| vrg.automaton.get_entry().contains(st)));
+        assert!(trace[3].iter().any(|st| vrg.automaton.get_exit().contains(st)));
+        assert_eq!(trace[2], trace[3]);
+        let dead = vrg.trace("ba");
+        assert_eq!(dead.len(), 3);
+        assert!(dead[1].is_empty() && dead[2].is_empty());
+        assert_eq!(vrg.trace("").len(), 1);
+    }
+
+    #[test]
+    fn test_validate_which() {
+        let mut vrg = Vregex::new("ab+a.").unwrap();
+        let exits_ab = vrg.validate_which("ab");
+        let exits_ac = vrg.validate_which("ac");
+        assert_eq!(exits_ab.len(), 2);
+        assert_eq!(exits_ac.len(), 1);
+        assert!(exits_ab.contains(&exits_ac[0]));
+        let exit_literal = *exits_ab.iter().find(|st| **st != exits_ac[0]).unwrap();
+        assert_eq!(vrg.automaton.label(exit_literal), Some("literal 'b' exit"));
+        assert!(vrg.validate_which("b").is_empty());
+    }
+
+    #[test]
+    fn test_find_with() {
+        let mut vrg = Vregex::new("a*").unwrap();
+        assert_eq!(vrg.find_with("aaa", MatchPolicy::LeftmostShortest), Some((0, 0)));
+        assert_eq!(vrg.find_with("aaa", MatchPolicy::LeftmostLongest), Some((0, 3)));
+        let mut vrg = Vregex::new("ab(c)?").unwrap();
+        assert_eq!(vrg.find_with("xabcd", MatchPolicy::LeftmostShortest), Some((1, 3)));
+        assert_eq!(vrg.find_with("xabcd", MatchPolicy::LeftmostLongest), Some((1, 4)));
+        assert_eq!(vrg.find_with("xbc", MatchPolicy::LeftmostShortest), None);
+        let mut end = Vregex::new("a*$").unwrap();
+        assert_eq!(end.find_with("baa", MatchPolicy::LeftmostShortest), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert_eq!(vrg.find_iter("ababxab"), vec![(0, 2), (2, 4), (5, 7)]);
+        assert_eq!(vrg.find_iter("xyz"), vec![]);
+
+        let mut vrg = Vregex::new("aa").unwrap();
+        assert_eq!(vrg.find_iter("aaaaa"), vec![(0, 2), (2, 4)]);
+
+        let mut vrg = Vregex::new("a*").unwrap();
+        assert_eq!(vrg.find_iter("ab"), vec![(0, 1), (1, 1), (2, 2)]);
+        assert_eq!(vrg.find_iter(""), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let vrg = Vregex::new("ab").unwrap();
+        let haystack = "xab".repeat(100_000);
+        let mut matches = vrg.matches(&haystack);
+        assert_eq!(matches.next(), Some((1, 3)));
+        assert_eq!(matches.next(), Some((4, 6)));
+        // Another iterator over the same regex starts from the beginning
+        assert_eq!(vrg.matches(&haystack).nth(2), Some((7, 9)));
+
+        // Zero-width matches move one char further, the end of the string included
+        let star = Vregex::new("a*").unwrap();
+        let all: Vec<(usize, usize)> = star.matches("b\u{e9}aa").collect();
+        assert_eq!(all, vec![(0, 0), (1, 1), (3, 5), (5, 5)]);
+        let mut done = star.matches("");
+        assert_eq!(done.next(), Some((0, 0)));
+        assert_eq!(done.next(), None);
+        assert_eq!(done.next(), None);
+    }
+
+    #[test]
+    fn test_find_overlapping() {
+        let mut vrg = Vregex::new("a*").unwrap();
+        assert_eq!(vrg.find_overlapping("aa"), vec![(0, 0), (0, 1), (0, 2), (1, 1), (1, 2), (2, 2)]);
+        let mut vrg = Vregex::new("aba").unwrap();
+        assert_eq!(vrg.find_overlapping("ababa"), vec![(0, 3), (2, 5)]);
+        assert!(vrg.find_overlapping("abb").is_empty());
+        let mut vrg = Vregex::new("a{1,}$").unwrap();
+        assert_eq!(vrg.find_overlapping("baa"), vec![(1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_captures() {
+        let mut vrg = Vregex::new("(a)(b)").unwrap();
+        assert_eq!(vrg.captures("ab"), Some(vec![Some((0, 2)), Some((0, 1)), Some((1, 2))]));
+        assert_eq!(vrg.captures("xxab"), Some(vec![Some((2, 4)), Some((2, 3)), Some((3, 4))]));
+        assert_eq!(vrg.captures("ba"), None);
+
+        // Nested groups are numbered by their opening parenthesis, a repeated group keeps its last span
+        let mut vrg = Vregex::new("x((a+b)c)*").unwrap();
+        assert_eq!(vrg.captures("xacbc"), Some(vec![Some((0, 5)), Some((3, 5)), Some((3, 4))]));
+
+        // A group skipped by the match has no span
+        let mut vrg = Vregex::new("(a)?b(c)?").unwrap();
+        assert_eq!(vrg.captures("b"), Some(vec![Some((0, 1)), None, None]));
+        assert_eq!(vrg.captures("ab"), Some(vec![Some((0, 2)), Some((0, 1)), None]));
+
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert_eq!(vrg.captures("ab"), Some(vec![Some((0, 2))]));
+    }
+
+    #[test]
+    fn test_captures_named() {
+        let mut vrg = Vregex::new("(?<first>a)(?<second>b)").unwrap();
+        let named = vrg.captures_named("xab").unwrap();
+        assert_eq!(named.len(), 2);
+        assert_eq!(named["first"], (1, 2));
+        assert_eq!(named["second"], (2, 3));
+        assert_eq!(vrg.captures_named("ba"), None);
+
+        // Named and unnamed groups share the numbering
+        let mut vrg = Vregex::new("(a)(?<x>b)?c").unwrap();
+        assert_eq!(vrg.captures("abc"), Some(vec![Some((0, 3)), Some((0, 1)), Some((1, 2))]));
+        assert_eq!(vrg.captures_named("ac"), Some(HashMap::new()));
+
+        assert!(matches!(Vregex::new("(?<x>a)(?<x>b)"), Err(VregexError::ParsingError { offset: 7, .. })));
+        assert!(matches!(Vregex::new("(?<x"), Err(VregexError::ParsingError { .. })));
+        let duplicate = Regex::Concat(
+            Box::new(Regex::NamedGroup("x".to_string(), 1, Box::new(Regex::Literal('a')))),
+            Box::new(Regex::NamedGroup("x".to_string(), 2, Box::new(Regex::Literal('b')))),
+        );
+        assert!(matches!(Vregex::from_ast(duplicate), Err(VregexError::InvalidAst { .. })));
+    }
+
+    #[test]
+    fn test_split() {
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert_eq!(vrg.split("1ab2ab3"), vec!["1", "2", "3"]);
+        assert_eq!(vrg.split("ab1ab"), vec!["", "1", ""]);
+        assert_eq!(vrg.split("abab"), vec!["", "", ""]);
+        assert_eq!(vrg.split("xyz"), vec!["xyz"]);
+        assert_eq!(vrg.split(""), vec![""]);
+
+        let mut vrg = Vregex::new("()").unwrap();
+        assert_eq!(vrg.split("ab"), vec!["", "a", "b", ""]);
+        let mut vrg = Vregex::new("[0-9]*").unwrap();
+        // find_iter() gives (0, 0), (1, 3), (3, 3) and (4, 4)
+        assert_eq!(vrg.split("a12b"), vec!["", "a", "", "b", ""]);
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let mut vrg = Vregex::new("ab").unwrap();
+        assert_eq!(vrg.replace_all("xaby", "Z"), "xZy");
+        assert_eq!(vrg.replace_all("abab", "Z"), "ZZ");
+        assert_eq!(vrg.replace_all("aabb", ""), "ab");
+        assert_eq!(vrg.replace_all("xyz", "Z"), "xyz");
+
+        let mut vrg = Vregex::new("()").unwrap();
+        assert_eq!(vrg.replace_all("ab", "-"), "-a-b-");
+        let mut vrg = Vregex::new("[0-9]{1,}").unwrap();
+        assert_eq!(vrg.replace_all("a12b3", "#"), "a#b#");
+    }
+
+    #[test]
+    fn test_count_matches() {
+        assert_eq!(Vregex::new("ab").unwrap().count_matches("ababab"), 3);
+        assert_eq!(Vregex::new("ab").unwrap().count_matches("xyz"), 0);
+        // Matches never overlap
+        assert_eq!(Vregex::new("aa").unwrap().count_matches("aaaaa"), 2);
+        assert_eq!(Vregex::new("aba").unwrap().count_matches("ababa"), 1);
+        assert_eq!(Vregex::new("a*").unwrap().count_matches("ab"), 3);
+        assert_eq!(Vregex::new("a*").unwrap().count_matches(""), 1);
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let vrg = Vregex::new("a(b+c)").unwrap();
+        assert_eq!(vrg.enumerate(2).collect::<Vec<_>>(), vec!["ab", "ac"]);
+        assert_eq!(vrg.enumerate(1).count(), 0);
+        let star = Vregex::new("a*").unwrap();
+        assert_eq!(star.enumerate(3).collect::<Vec<_>>(), vec!["", "a", "aa", "aaa"]);
+        assert_eq!(star.enumerate(usize::MAX).count(), MAX_ENUMERATED);
+    }
+
+    #[test]
+    fn test_shortest_match() {
+        assert_eq!(Vregex::new("a*b").unwrap().shortest_match(), Some("b".to_string()));
+        assert_eq!(Vregex::new("(ab)*").unwrap().shortest_match(), Some(String::new()));
+        assert_eq!(Vregex::new("ab{2,}c+d").unwrap().shortest_match(), Some("d".to_string()));
+        assert_eq!(Vregex::new("a(()()b)?c{2}").unwrap().shortest_match(), Some("acc".to_string()));
+        assert_eq!(Vregex::new("[^a-zA-Z0-9]").unwrap().shortest_match(), None);
+    }
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(Vregex::new("(a+b)(a+b)").unwrap().count_words(2), 4);
+        let star = Vregex::new("a*").unwrap();
+        for len in [0, 1, 2, 10] {
+            assert_eq!(star.count_words(len), 1);
+        }
+    }
+
+    #[test]
+    fn test_difference() {
+        let mut vrg = Vregex::new("a*").unwrap().difference(&Vregex::new("aa").unwrap());
+        assert!(vrg.validate("").unwrap());
+        assert!(vrg.validate("a").unwrap());
+        assert!(vrg.validate("aaa").unwrap());
+        assert!(!vrg.validate("aa").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+        // Chars of self outside of the default universe are kept
+        let mut vrg = Vregex::new("\\*+b").unwrap().difference(&Vregex::new("b").unwrap());
+        assert!(vrg.validate("*").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let ab = Vregex::new("ab").unwrap();
+        let any = Vregex::new("a.").unwrap();
+        assert!(ab.is_subset_of(&any));
+        assert!(!any.is_subset_of(&ab));
+        // Equal languages are subsets of each other
+        let star = Vregex::new("(a+b)*").unwrap();
+        let nested = Vregex::new("(a*b*)*").unwrap();
+        assert!(star.is_subset_of(&nested));
+        assert!(nested.is_subset_of(&star));
+        // Incomparable
+        let a_first = Vregex::new("ab*").unwrap();
+        let b_first = Vregex::new("a*b").unwrap();
+        assert!(!a_first.is_subset_of(&b_first));
+        assert!(!b_first.is_subset_of(&a_first));
+        // A char outside of the universe of other is not matched by its .
+        assert!(!Vregex::new("a\\*").unwrap().is_subset_of(&any));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let left = Vregex::new("a*b").unwrap();
+        assert!(left.overlaps(&Vregex::new("ba*").unwrap()));
+        assert!(left.overlaps(&Vregex::new("(aa)*b").unwrap()));
+        assert!(!left.overlaps(&Vregex::new("ba{1,}").unwrap()));
+        assert!(!left.overlaps(&Vregex::new("a*").unwrap()));
+        let disjoint = Vregex::new("ab").unwrap();
+        assert_eq!(left.overlaps(&disjoint), !left.intersect(&disjoint).matches_nothing());
+    }
+
+    #[test]
+    fn test_equivalent() {
+        assert!(Vregex::new("a*").unwrap().equivalent(&Vregex::new("a*a*").unwrap()));
+        assert!(Vregex::new("a*").unwrap().equivalent(&Vregex::new("(a*)*").unwrap()));
+        assert!(Vregex::new("a?").unwrap().equivalent(&Vregex::new("a{0,1}").unwrap()));
+        assert!(Vregex::new("(a+b)c").unwrap().equivalent(&Vregex::new("ac+bc").unwrap()));
+        assert!(!Vregex::new("a*").unwrap().equivalent(&Vregex::new("aa*").unwrap()));
+        assert!(!Vregex::new("a").unwrap().equivalent(&Vregex::new("a?").unwrap()));
+        assert!(!Vregex::new("ab").unwrap().equivalent(&Vregex::new("ba").unwrap()));
+    }
+
+    #[test]
+    fn test_matches_nothing() {
+        assert!(Vregex::new("[^a-zA-Z0-9]").unwrap().matches_nothing());
+        assert!(Vregex::new("a[^a-zA-Z0-9]*b[^a-zA-Z0-9]").unwrap().matches_nothing());
+        assert!(!Vregex::new("a[^a-zA-Z0-9]*b").unwrap().matches_nothing());
+        assert!(!Vregex::new("a*").unwrap().matches_nothing());
+    }
+
+    #[test]
+    fn test_intersect() {
+        let mut vrg = Vregex::new("a*b*").unwrap().intersect(&Vregex::new("a+ab").unwrap());
+        assert!(vrg.validate("a").unwrap());
+        assert!(vrg.validate("ab").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("ab?").unwrap()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let vrg = Vregex::new("ab+c").unwrap();
+        let json = serde_json::to_string(&vrg.automaton).unwrap();
+        let mut restored = Vregex::new("z").unwrap();
+        restored.automaton = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.automaton.get_entry(), vrg.automaton.get_entry());
+        assert_eq!(restored.automaton.get_exit(), vrg.automaton.get_exit());
+        assert!(restored.validate("ab").unwrap());
+        assert!(restored.validate("c").unwrap());
+        assert!(!restored.validate("a").unwrap());
+        assert!(!restored.validate("abc").unwrap());
+        assert!(!restored.validate("").unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_rejects_unknown_states() {
+        let json = serde_json::to_value(&Vregex::new("ab").unwrap().automaton).unwrap();
+        let corrupt = |pointer: &str| {
+            let mut json = json.clone();
+            *json.pointer_mut(pointer).unwrap() = serde_json::json!(99);
+            serde_json::from_value::<automaton::Automaton<char>>(json)
+        };
+        assert!(corrupt("/machine/states/0/transitions/a/0").is_err());
+        assert!(corrupt("/machine/states/1/eps_transitions/0").is_err());
+        assert!(corrupt("/entry/0").is_err());
+        assert!(corrupt("/exit/0").is_err());
+        let err = corrupt("/machine/states/0/transitions/a/0").unwrap_err();
+        assert!(err.to_string().contains("No state of id 99"), "{}", err);
+        assert!(serde_json::from_value::<automaton::Automaton<char>>(json).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_is_reproducible() {
+        let json = serde_json::to_string(&Vregex::new("(a+b+[c-f])*ab").unwrap().automaton).unwrap();
+        for _ in 0..5 {
+            assert_eq!(serde_json::to_string(&Vregex::new("(a+b+[c-f])*ab").unwrap().automaton).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_validate_long_input() {
+        let mut vrg = Vregex::new("a*").unwrap();
+        let long = "a".repeat(10_000);
+        assert!(vrg.validate(&long).unwrap());
+        assert!(!vrg.validate(&(long + "b")).unwrap());
+    }
+
+    #[test]
+    fn test_stream_validate() {
+        let mut vrg = Vregex::new("(ab+c)*d").unwrap();
+        for s in ["d", "abcd", "cabd", "ab", "", "abce"] {
+            let expected = vrg.validate(s).unwrap();
+            assert_eq!(vrg.stream_validate(s.chars()), expected, "{}", s);
+            for c in s.chars() {
+                vrg.feed(c);
+            }
+            assert_eq!(vrg.finish(), expected, "{}", s);
+        }
+        // Nothing past the first char can match, so the rest is never read
+        let mut pulled = 0;
+        assert!(!vrg.stream_validate(std::iter::repeat_n('x', 1000).inspect(|_| pulled += 1)));
+        assert_eq!(pulled, 1);
+    }
+
+    #[test]
+    fn test_validate_reader() {
+        // Hands out one byte per read so that multi-byte chars are split across reads
+        struct OneByte<'a>(&'a [u8]);
+        impl std::io::Read for OneByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.0.split_first() {
+                    Some((b, rest)) if !buf.is_empty() => {
+                        buf[0] = *b;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        // é and ß, two bytes each in UTF-8
+        let mut vrg = Vregex::new("a(\\xE9+\\xDF)*b").unwrap();
+        for s in ["ab", "aéßb", "aéb", "a", "aéx", ""] {
+            let expected = vrg.validate(s).unwrap();
+            assert_eq!(vrg.validate_reader(std::io::Cursor::new(s.as_bytes())).unwrap(), expected, "{}", s);
+            assert_eq!(vrg.validate_reader(OneByte(s.as_bytes())).unwrap(), expected, "{}", s);
+        }
+
+        let long = format!("a{}b", "é".repeat(10_000));
+        assert!(vrg.validate_reader(std::io::Cursor::new(long.as_bytes())).unwrap());
+
+        // Truncated é
+        let err = vrg.validate_reader(std::io::Cursor::new(&b"a\xc3"[..])).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(vrg.validate_reader(OneByte(b"a\xffb")).is_err());
+    }
+
+    #[test]
+    fn test_matcher() {
+        fn assert_sync<T: Sync>(_: &T) {}
+        let vrg = Vregex::new("((ab)+c)*(z+x)*").unwrap();
+        assert_sync(&vrg);
+        let mut first = vrg.matcher();
+        let mut second = vrg.matcher();
+        assert!(first.validate("abzx").unwrap());
+        assert!(!second.validate("ba").unwrap());
+        assert!(first.validate("ccc").unwrap());
+
+        std::thread::scope(|scope| {
+            let accepted = scope.spawn(|| vrg.matcher().validate("ababab").unwrap());
+            let rejected = scope.spawn(|| vrg.matcher().validate("abz a").unwrap());
+            assert!(accepted.join().unwrap());
+            assert!(!rejected.join().unwrap());
+        });
+    }
+
+    #[test]
+    fn test_new_invalid() {
+        assert!(matches!(Vregex::new("(a"), Err(VregexError::ParsingError { .. })));
+        assert!(matches!(Vregex::new("*"), Err(VregexError::ParsingError { .. })));
+        assert!(Vregex::new("a(b)").is_ok());
+    }
+
+    #[test]
+    fn test_parsing_error_offset() {
+        match Vregex::new("ab)") {
+            Err(VregexError::ParsingError { offset, message }) => {
+                assert_eq!(offset, 2);
+                assert_eq!(message, "unmatched ')'");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(Vregex::new("ab{3,1}"), Err(VregexError::ParsingError { offset: 2, .. })));
+        assert!(matches!(Vregex::new("a(b"), Err(VregexError::ParsingError { offset: 1, .. })));
+        match Vregex::new("ab{3") {
+            Err(VregexError::ParsingError { offset, message }) => {
+                assert_eq!(offset, 4);
+                assert_eq!(message, "unexpected end of regex");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            Vregex::new("éa)").unwrap_err().to_string(),
+            "Parsing error at 0: unexpected character 'é'",
+        );
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let mut vrg = Vregex::new("()").unwrap();
+        assert!(vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+
+        assert!(Vregex::new("a()").unwrap().equivalent(&Vregex::new("a").unwrap()));
+        let mut vrg = Vregex::new("a()*").unwrap();
+        assert!(vrg.validate("a").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        let mut vrg = Vregex::new("a+()").unwrap();
+        assert!(vrg.validate("").unwrap());
+        assert!(vrg.validate("a").unwrap());
+        assert_eq!(Vregex::new("x()").unwrap().captures("x"), Some(vec![Some((0, 1)), Some((1, 1))]));
+    }
+
+    #[test]
+    fn test_new_extended() {
+        let mut extended = Vregex::new_extended("( ab )+ c\\ d").unwrap();
+        let normal = Vregex::new("(ab)+c\\x20d").unwrap();
+        assert!(extended.equivalent(&normal));
+        assert!(extended.validate("ab").unwrap());
+        assert!(extended.validate("c d").unwrap());
+        assert!(!extended.validate("cd").unwrap());
+        assert!(matches!(Vregex::new_extended("a b )"), Err(VregexError::ParsingError { offset: 4, .. })));
+        assert!(Vregex::new("a b").is_err());
+    }
+
+    #[test]
+    fn test_default() {
+        let mut vrg = Vregex::default();
+        assert!(vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("").unwrap()));
+    }
+
+    #[test]
+    fn test_empty_regex() {
+        let mut vrg = Vregex::new("").unwrap();
+        assert!(vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("()").unwrap()));
+        assert_eq!(vrg.find("ab"), Some((0, 0)));
+        assert!(matches!(Vregex::new(" "), Err(VregexError::ParsingError { offset: 0, .. })));
+    }
+
+    #[test]
+    fn test_from_ast() {
+        use Regex::*;
+        let ast = Concat(Box::new(Literal('a')), Box::new(Star(Box::new(Literal('b')))));
+        let mut vrg = Vregex::from_ast(ast).unwrap();
+        assert!(vrg.validate("abbb").unwrap());
+        assert!(vrg.validate("a").unwrap());
+        assert!(!vrg.validate("b").unwrap());
+
+        let reversed = Repeat { inner: Box::new(Literal('a')), min: 3, max: Some(1) };
+        assert!(matches!(Vregex::from_ast(reversed), Err(VregexError::InvalidAst { .. })));
+    }
+
+    #[test]
+    fn test_any_of() {
+        let mut vrg = Vregex::any_of(&["ab", "cd"]).unwrap();
+        assert!(vrg.validate("ab").unwrap());
+        assert!(vrg.validate("cd").unwrap());
+        assert!(!vrg.validate("ad").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("ab+cd").unwrap()));
+        // . ranges over the symbols of every pattern
+        assert!(Vregex::any_of(&["\\*", "a."]).unwrap().validate("a*").unwrap());
+        assert_eq!(Vregex::any_of(&["^a", "^b"]).unwrap().find("ca"), None);
+        assert!(Vregex::any_of(&[]).unwrap().matches_nothing());
+
+        assert!(matches!(
+            Vregex::any_of(&["ab", "c(", "d"]),
+            Err(VregexError::PatternError { index: 1, offset: 1, .. })
+        ));
+        assert!(matches!(Vregex::any_of(&["^a", "b"]), Err(VregexError::PatternError { index: 1, .. })));
+    }
+
+    #[test]
+    fn test_dot_matches_newline() {
+        let mut strict = Vregex::new_with_options("a.b", Options::default()).unwrap();
+        assert!(!strict.validate("a\nb").unwrap());
+        assert!(strict.validate("axb").unwrap());
+        // Even when \n is written in the regex
+        let mut written = Vregex::new_with_options("\\x0A.", Options::default()).unwrap();
+        assert!(!written.validate("\n\n").unwrap());
+        assert!(written.validate("\na").unwrap());
+
+        let options = Options { dot_matches_newline: true };
+        let mut dotall = Vregex::new_with_options("a.b", options.clone()).unwrap();
+        assert!(dotall.validate("a\nb").unwrap());
+        assert!(dotall.validate("axb").unwrap());
+        assert!(Vregex::new_with_options("[^a]", options).unwrap().validate("\n").unwrap());
+    }
+
+    #[test]
+    fn test_with_options() {
+        let options = VregexOptions::new().case_insensitive(true).extended(true);
+        let mut vrg = Vregex::with_options("( ab )+ c", options).unwrap();
+        assert!(vrg.validate("AB").unwrap());
+        assert!(vrg.validate("aB").unwrap());
+        assert!(vrg.validate("C").unwrap());
+        assert!(!vrg.validate("a b").unwrap());
+        assert!(vrg.equivalent(&Vregex::new("[aA][bB]+[cC]").unwrap()));
+
+        assert_eq!(VregexOptions::default(), VregexOptions::new());
+        let limited = VregexOptions::new().state_limit(10).dot_matches_newline(true);
+        assert!(matches!(Vregex::with_options("a{100}", limited.clone()), Err(VregexError::StateLimitExceeded { limit: 10 })));
+        assert!(Vregex::with_options(".", limited).unwrap().validate("\n").unwrap());
+        // new_with_options() is with_options() restricted to what Options holds
+        let old = Vregex::new_with_options("a.b", Options { dot_matches_newline: true }).unwrap();
+        assert!(old.equivalent(&Vregex::with_options("a.b", VregexOptions::new().dot_matches_newline(true)).unwrap()));
+        // The universe given is what . matches
+        assert!(Vregex::with_universe("a.b", ['\n']).unwrap().validate("a\nb").unwrap());
+    }
+
+    #[test]
+    fn test_per_scalar_matching() {
+        // Without normalization the decomposed form is two chars
+        let mut vrg = Vregex::new("\\xE9").unwrap();
+        assert!(vrg.validate("\u{e9}").unwrap());
+        assert!(!vrg.validate("e\u{301}").unwrap());
+    }
+
+    #[cfg(feature = "normalization")]
+    #[test]
+    fn test_normalize() {
+        let mut vrg = Vregex::with_options("caf\\xE9", VregexOptions::new().normalize(Normalization::Nfc)).unwrap();
+        assert!(vrg.validate("caf\u{e9}").unwrap());
+        assert!(vrg.validate("cafe\u{301}").unwrap());
+        assert!(!vrg.validate("cafe").unwrap());
+        assert!(vrg.is_match("un cafe\u{301} noir"));
+        // Every method reading the string whole normalizes it
+        let decomposed = "cafe\u{301}";
+        assert!(vrg.matcher().validate(decomposed).unwrap());
+        assert!(vrg.validate_bounded(decomposed, 100).unwrap());
+        assert!(!vrg.validate_which(decomposed).is_empty());
+        assert!(vrg.stream_validate(decomposed.chars()));
+        assert!(vrg.is_prefix(decomposed));
+        assert!(Vregex::with_options("\\xE9", VregexOptions::new().normalize(Normalization::Nfc)).unwrap().is_prefix("e\u{301}"));
+        assert!(vrg.validate_reader(std::io::Cursor::new(decomposed.as_bytes())).unwrap());
+        assert!(!vrg.validate_reader(std::io::Cursor::new("cafe".as_bytes())).unwrap());
+        // The parser reads no char NFC could change, so the regex needs no normalizing
+        assert!(Vregex::new("caf\u{e9}").is_err());
+    }
+
+    #[test]
+    fn test_failed_build_gives_no_regex() {
+        // A build that fails gives no Vregex at all, so reads only ever run on a built automaton
+        assert!(matches!(Vregex::new("(a"), Err(VregexError::ParsingError { .. })));
+        let reversed = Regex::Repeat { inner: Box::new(Regex::Literal('a')), min: 3, max: Some(1) };
+        assert!(matches!(Vregex::from_ast(reversed), Err(VregexError::InvalidAst { .. })));
+        assert!(matches!(Vregex::with_state_limit("a{20}", 10), Err(VregexError::StateLimitExceeded { limit: 10 })));
+        // A regex matching nothing is built, with an entry state, and no match is an answer rather than an error
+        // The automaton cannot be swapped for one without entry since it is only lent out by automaton()
+        let mut vrg = Vregex::new("[^a-zA-Z0-9]").unwrap();
+        assert_eq!(vrg.automaton().get_entry().len(), 1);
+        assert!(!vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+    }
+
+    #[test]
+    fn test_alphabet() {
+        assert_eq!(Vregex::new("a+b").unwrap().alphabet(), HashSet::from(['a', 'b']));
+        assert_eq!(Vregex::new("(ab)*c?").unwrap().alphabet(), HashSet::from(['a', 'b', 'c']));
+        assert_eq!(Vregex::new("[a-c]\\*").unwrap().alphabet(), HashSet::from(['a', 'b', 'c', '*']));
+        assert_eq!(Vregex::with_universe(".", "xy".chars()).unwrap().alphabet(), HashSet::from(['x', 'y']));
+        assert!(Vregex::new("()").unwrap().alphabet().is_empty());
+    }
+
+    #[test]
+    fn test_state_limit() {
+        assert!(matches!(Vregex::with_state_limit("a{1000}{1000}", 100), Err(VregexError::StateLimitExceeded { limit: 100 })));
+        assert!(matches!(Vregex::with_state_limit("(ab)*", 2), Err(VregexError::StateLimitExceeded { .. })));
+        let mut vrg = Vregex::with_state_limit("(ab)*", 100).unwrap();
+        assert!(vrg.validate("abab").unwrap());
+        assert!(vrg.automaton.nb_state() <= 100);
+        assert!(matches!(Vregex::with_state_limit("(a", 100), Err(VregexError::ParsingError { .. })));
+
+        // Huge bounds fail before anything is unrolled
+        assert!(matches!(Vregex::with_state_limit("a{4000000000}", 100), Err(VregexError::StateLimitExceeded { limit: 100 })));
+        assert!(matches!(Vregex::with_state_limit("a{0,200000000}", 100), Err(VregexError::StateLimitExceeded { limit: 100 })));
+        assert!(matches!(Vregex::with_state_limit("(ab){3000000000,}", 100), Err(VregexError::StateLimitExceeded { limit: 100 })));
+        assert!(Vregex::with_state_limit("a{50}", 100).is_ok());
+        let empty = Regex::Repeat { inner: Box::new(Regex::Epsilon), min: 90, max: None };
+        assert!(Vregex::empty(HashSet::new()).build_ast(empty, Some(100), false).is_ok());
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut vrg = Vregex::new_case_insensitive("abc").unwrap();
+        assert!(vrg.validate("ABC").unwrap());
+        assert!(vrg.validate("Abc").unwrap());
+        assert!(vrg.validate("abc").unwrap());
+        assert!(!vrg.validate("abd").unwrap());
+
+        let mut vrg = Vregex::new_case_insensitive("X[a-c]+9\\*").unwrap();
+        assert!(vrg.validate("xB").unwrap());
+        assert!(vrg.validate("9*").unwrap());
+        assert!(!vrg.validate("xd").unwrap());
+
+        let mut vrg = Vregex::new_case_insensitive("[^a]").unwrap();
+        assert!(!vrg.validate("A").unwrap());
+        assert!(vrg.validate("b").unwrap());
+
+        let mut vrg = Vregex::new("abc").unwrap();
+        assert!(!vrg.validate("ABC").unwrap());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let mut vrg: Vregex = "ab+c".parse().unwrap();
+        assert!(vrg.validate("ab").unwrap());
+        assert!(vrg.validate("c").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(matches!("(".parse::<Vregex>(), Err(VregexError::ParsingError { .. })));
+        assert!(matches!("a{2,1}".parse::<Vregex>(), Err(VregexError::ParsingError { .. })));
+    }
+
     #[test]
     fn test_all() {
-        let mut vrg = Vregex::new("((ab)+c)*(z+x)*");
+        let mut vrg = Vregex::new("((ab)+c)*(z+x)*").unwrap();
         assert!(vrg.validate("c").unwrap());
         assert!(vrg.validate("ab").unwrap());
         assert!(vrg.validate("abzzzzzxxxxx").unwrap());
@@ -1,9 +1,29 @@
+mod binary;
+mod builder;
+mod captures;
+mod dfa;
+mod dot;
+#[cfg(feature = "serde")]
+mod ordered;
 mod state_machine;
+mod state_set;
+mod words;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use state_set::StateSet;
+
+pub use builder::{AutomatonBuilder, StateId};
+pub use captures::GroupTag;
+pub use state_machine::{StateMachine, StateMachineError};
 
 #[derive(Debug)]
 pub enum AutomatonError {
     InternalFailure,
+    // Bytes given to Automaton::from_bytes() are not the output of to_bytes()
+    InvalidEncoding,
+    // A read was started on an automaton without any entry state, e.g. one that was never built,
+    // which would reject everything without telling why
+    NoEntry,
 }
 
 impl From<state_machine::StateMachineError> for AutomatonError {
@@ -12,16 +32,82 @@ impl From<state_machine::StateMachineError> for AutomatonError {
     }
 }
 
+// Symbol transitions and epsilon targets leaving a state, see Automaton::outgoing()
+pub type Outgoing<T> = (Vec<(T, usize)>, Vec<usize>);
+
+// States that take no part in any accepted word, see Automaton::diagnostics()
+// A state can be in both sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    // Not reachable from any entry
+    pub unreachable: HashSet<usize>,
+    // No exit can be reached from them
+    pub dead: HashSet<usize>,
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "LoadedAutomaton<T>", bound(deserialize = "T: serde::Deserialize<'de>")))]
 pub struct Automaton<T>
 where
     T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
 {
-    nb_state: usize,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "ordered::sorted_set"))]
     entry: HashSet<usize>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "ordered::sorted_set"))]
     exit: HashSet<usize>,
-    current_states: HashSet<usize>,
+    // Only meaningful during a read, so it is not part of the serialized automaton
+    #[cfg_attr(feature = "serde", serde(skip))]
+    current_states: StateSet,
     machine: state_machine::StateMachine<T>,
+    // Optional description of where a state comes from, e.g. "literal 'a' entry", only used for debugging
+    #[cfg_attr(feature = "serde", serde(default, serialize_with = "ordered::sorted_labels"))]
+    labels: HashMap<usize, String>,
+}
+
+// What a serialized Automaton holds, checked before becoming one so that entries, exits and labels name existing states
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct LoadedAutomaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    entry: HashSet<usize>,
+    exit: HashSet<usize>,
+    machine: state_machine::StateMachine<T>,
+    #[serde(default)]
+    labels: HashMap<usize, String>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<LoadedAutomaton<T>> for Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    type Error = StateMachineError;
+
+    fn try_from(loaded: LoadedAutomaton<T>) -> Result<Self, Self::Error> {
+        let machine = loaded.machine;
+        if let Some(st) = loaded.entry.iter().chain(&loaded.exit).chain(loaded.labels.keys()).find(|st| !machine.is_valid_state_id(**st)) {
+            return Err(StateMachineError::InvalidStateId(*st));
+        }
+        Ok(Automaton {
+            entry: loaded.entry,
+            exit: loaded.exit,
+            current_states: StateSet::new(),
+            machine,
+            labels: loaded.labels,
+        })
+    }
+}
+
+impl<T> Default for Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    fn default() -> Self {
+        Automaton::new()
+    }
 }
 
 impl<T> Automaton<T>
@@ -30,21 +116,24 @@ where
 {
     pub fn new() -> Automaton<T> {
         Automaton {
-            nb_state: 0,
             entry: HashSet::new(),
             exit: HashSet::new(),
-            current_states: HashSet::new(),
+            current_states: StateSet::new(),
             machine: state_machine::StateMachine::new(),
+            labels: HashMap::new(),
         }
     }
 
+    // The machine owns the states, so the count cannot drift from the ids it hands out
+    pub fn nb_state(&self) -> usize {
+        self.machine.nb_state()
+    }
+
     pub fn add_state(&mut self) -> usize {
-        self.nb_state += 1;
         self.machine.add_state()
     }
 
     pub fn add_n_state(&mut self, n: usize) -> Vec<usize> {
-        self.nb_state += n;
         self.machine.add_n_state(n)
     }
 
@@ -57,6 +146,19 @@ where
         }
     }
 
+    pub fn set_label(&mut self, i: usize, label: String) -> Result<(), AutomatonError> {
+        if !self.machine.is_valid_state_id(i) {
+            Err(AutomatonError::InternalFailure)
+        } else {
+            self.labels.insert(i, label);
+            Ok(())
+        }
+    }
+
+    pub fn label(&self, i: usize) -> Option<&str> {
+        self.labels.get(&i).map(String::as_str)
+    }
+
     pub fn reset_entry(&mut self) {
         self.entry = HashSet::new();
     }
@@ -65,6 +167,11 @@ where
         self.entry.clone()
     }
 
+    // Same as get_entry() without cloning, when entries are only read
+    pub fn entry_iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entry.iter().copied()
+    }
+
     pub fn add_exit(&mut self, i: usize) -> Result<(), AutomatonError> {
         if !self.machine.is_valid_state_id(i) {
             Err(AutomatonError::InternalFailure)
@@ -78,6 +185,11 @@ where
         self.exit.clone()
     }
 
+    // Same as get_exit() without cloning, when exits are only read
+    pub fn exit_iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.exit.iter().copied()
+    }
+
     pub fn reset_exit(&mut self) {
         self.exit = HashSet::new();
     }
@@ -92,34 +204,1089 @@ where
         Ok(())
     }
 
-    pub fn init_for_read(&mut self) {
-        self.current_states = self.entry.clone();
+    // Symbol transitions (symbol, target) and epsilon targets leaving state,
+    // ordered by target then symbol so that the result does not depend on hashing
+    // Fails with InternalFailure if state is not a state of self
+    pub fn outgoing(&self, state: usize) -> Result<Outgoing<T>, AutomatonError> {
+        if !self.machine.is_valid_state_id(state) {
+            return Err(AutomatonError::InternalFailure);
+        }
+        let mut transitions: Vec<(T, usize)> = self.machine.transitions_from(state).map(|(v, to)| (*v, to)).collect();
+        transitions.sort_by_cached_key(|(v, to)| (*to, format!("{:?}", v)));
+        let mut eps_targets: Vec<usize> = self.machine.eps_transitions_from(state).collect();
+        eps_targets.sort_unstable();
+        Ok((transitions, eps_targets))
     }
 
-    pub fn read(&mut self, v: &T) {
-        let next_states: HashSet<usize> = self.current_states
-            .iter()
-            .flat_map(|st| {
-                self.machine.apply_transition(*st, v)
-            })
+    // Automaton accepting the words accepted by self or by other
+    pub fn union(&self, other: &Automaton<T>) -> Automaton<T> {
+        let mut out = Automaton::new();
+        for autom in [self, other] {
+            let offset = out.absorb(autom);
+            out.entry.extend(autom.entry.iter().map(|st| st + offset));
+            out.exit.extend(autom.exit.iter().map(|st| st + offset));
+        }
+        out
+    }
+
+    // Automaton accepting a word of self followed by a word of other
+    // Every exit of self gets an epsilon transition to every entry of other
+    pub fn concat(&self, other: &Automaton<T>) -> Automaton<T> {
+        let mut out = Automaton::new();
+        let left = out.absorb(self);
+        let right = out.absorb(other);
+        out.entry.extend(self.entry.iter().map(|st| st + left));
+        out.exit.extend(other.exit.iter().map(|st| st + right));
+        for from in self.exit.iter() {
+            for to in other.entry.iter() {
+                // Both ids were shifted into out by absorb so this cannot fail
+                let _ = out.add_eps_transition(from + left, to + right);
+            }
+        }
+        out
+    }
+
+    // Automaton accepting any number of words of self in a row, the empty word included
+    // A new entry and a new exit wrap the states of self, same construction as a * in a regex
+    pub fn star(&self) -> Automaton<T> {
+        let mut out = Automaton::new();
+        let offset = out.absorb(self);
+        let new_entry = out.add_state();
+        let new_exit = out.add_state();
+        out.entry.insert(new_entry);
+        out.exit.insert(new_exit);
+        // Every id comes from add_state or was shifted into out by absorb so this cannot fail
+        let _ = out.add_eps_transition(new_entry, new_exit);
+        for entry in self.entry.iter() {
+            let _ = out.add_eps_transition(new_entry, entry + offset);
+        }
+        for exit in self.exit.iter() {
+            let _ = out.add_eps_transition(exit + offset, new_exit);
+            for entry in self.entry.iter() {
+                let _ = out.add_eps_transition(exit + offset, entry + offset);
+            }
+        }
+        out
+    }
+
+    // Automaton accepting the words accepted by both self and other
+    // Its states are the reachable pairs (state of self, state of other): a symbol moves both sides at once,
+    // an epsilon transition of either side moves only that side, and a pair is an exit iff both sides are
+    pub fn intersect(&self, other: &Automaton<T>) -> Automaton<T> {
+        let mut out = Automaton::new();
+        let mut ids: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut todo = Vec::new();
+        for l in self.entry.iter() {
+            for r in other.entry.iter() {
+                let id = out.add_state();
+                out.entry.insert(id);
+                ids.insert((*l, *r), id);
+                todo.push((*l, *r));
+            }
+        }
+
+        while let Some((l, r)) = todo.pop() {
+            let from = ids[&(l, r)];
+            if self.exit.contains(&l) && other.exit.contains(&r) {
+                out.exit.insert(from);
+            }
+            let mut edges: Vec<(Option<T>, (usize, usize))> = Vec::new();
+            for l_to in self.machine.eps_transitions_from(l) {
+                edges.push((None, (l_to, r)));
+            }
+            for r_to in other.machine.eps_transitions_from(r) {
+                edges.push((None, (l, r_to)));
+            }
+            for (v, l_to) in self.machine.transitions_from(l) {
+                for (_, r_to) in other.machine.transitions_from(r).filter(|(w, _)| *w == v) {
+                    edges.push((Some(*v), (l_to, r_to)));
+                }
+            }
+            for (v, pair) in edges {
+                let to = match ids.get(&pair) {
+                    Some(id) => *id,
+                    None => {
+                        let id = out.add_state();
+                        ids.insert(pair, id);
+                        todo.push(pair);
+                        id
+                    }
+                };
+                // Both ids come from add_state so this cannot fail
+                let _ = match v {
+                    Some(v) => out.add_transition(from, to, v),
+                    None => out.add_eps_transition(from, to),
+                };
+            }
+        }
+        out
+    }
+
+    // True iff some word is accepted by both self and other
+    // Walks the same product as intersect() but without building it, stopping at the first pair of exits reached
+    pub fn overlaps(&self, other: &Automaton<T>) -> bool {
+        let mut seen: HashSet<(usize, usize)> = HashSet::new();
+        let mut todo = Vec::new();
+        for l in self.entry.iter() {
+            for r in other.entry.iter() {
+                if seen.insert((*l, *r)) {
+                    todo.push((*l, *r));
+                }
+            }
+        }
+
+        while let Some((l, r)) = todo.pop() {
+            if self.exit.contains(&l) && other.exit.contains(&r) {
+                return true;
+            }
+            let mut next = Vec::new();
+            next.extend(self.machine.eps_transitions_from(l).map(|l_to| (l_to, r)));
+            next.extend(other.machine.eps_transitions_from(r).map(|r_to| (l, r_to)));
+            for (v, l_to) in self.machine.transitions_from(l) {
+                next.extend(other.machine.transitions_from(r).filter(|(w, _)| *w == v).map(|(_, r_to)| (l_to, r_to)));
+            }
+            for pair in next {
+                if seen.insert(pair) {
+                    todo.push(pair);
+                }
+            }
+        }
+        false
+    }
+
+    // Automaton accepting the mirror image of every word accepted by self
+    // Same states with every transition flipped, entries and exits swapped
+    pub fn reverse(&self) -> Automaton<T> {
+        let mut out = Automaton::new();
+        out.add_n_state(self.machine.nb_state());
+        // Ids are shared with self, whose states were all added to out, so this cannot fail
+        for (from, v, to) in self.machine.transitions() {
+            let _ = out.add_transition(to, from, *v);
+        }
+        for (from, to) in self.machine.eps_transitions() {
+            let _ = out.add_eps_transition(to, from);
+        }
+        out.entry = self.exit.clone();
+        out.exit = self.entry.clone();
+        out.labels = self.labels.clone();
+        out
+    }
+
+    // Same automaton over another alphabet, every transition symbol going through f, e.g. char to u8 or case folding
+    // Symbols mapped to the same value merge their transitions, which can make the result non deterministic
+    pub fn map_symbols<U, F>(&self, f: F) -> Automaton<U>
+    where
+        U: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+        F: Fn(&T) -> U,
+    {
+        let mut out = Automaton::new();
+        out.add_n_state(self.machine.nb_state());
+        // Ids are shared with self, whose states were all added to out, so this cannot fail
+        for (from, v, to) in self.machine.transitions() {
+            let _ = out.add_transition(from, to, f(v));
+        }
+        for (from, to) in self.machine.eps_transitions() {
+            let _ = out.add_eps_transition(from, to);
+        }
+        out.entry = self.entry.clone();
+        out.exit = self.exit.clone();
+        out.labels = self.labels.clone();
+        out
+    }
+
+    // Copies the states of other into self and returns the offset applied to their ids
+    fn absorb(&mut self, other: &Automaton<T>) -> usize {
+        let offset = self.machine.absorb(&other.machine);
+        for (st, label) in other.labels.iter() {
+            self.labels.insert(st + offset, label.clone());
+        }
+        offset
+    }
+
+    // Every symbol labelling at least one transition
+    pub fn alphabet(&self) -> HashSet<T> {
+        self.machine.symbols().into_iter().copied().collect()
+    }
+
+    // One line overview of the automaton, more readable than Debug for big ones, e.g.
+    // "states: 4, entries: 1, exits: 1, transitions: 2, epsilon transitions: 1, deterministic: no"
+    pub fn summary(&self) -> String {
+        format!(
+            "states: {}, entries: {}, exits: {}, transitions: {}, epsilon transitions: {}, deterministic: {}",
+            self.machine.nb_state(),
+            self.entry.len(),
+            self.exit.len(),
+            self.machine.transitions().count(),
+            self.machine.eps_transitions().count(),
+            if self.is_deterministic() { "yes" } else { "no" },
+        )
+    }
+
+    // Single entry, no epsilon transition and at most one target per state and symbol,
+    // i.e. reads never need to track more than one state and to_dfa() would be of no use
+    pub fn is_deterministic(&self) -> bool {
+        if self.entry.len() != 1 || self.machine.eps_transitions().next().is_some() {
+            return false;
+        }
+        (0..self.machine.nb_state()).all(|st| {
+            let mut symbols = HashSet::new();
+            self.machine.transitions_from(st).all(|(v, _)| symbols.insert(v))
+        })
+    }
+
+    // True iff no exit state can be reached from an entry state, i.e. no word is accepted
+    pub fn is_empty_language(&self) -> bool {
+        self.reachable_states().is_disjoint(&self.exit)
+    }
+
+    // True iff finitely many words are accepted, i.e. no cycle reading a symbol lies on a path from an entry to an exit
+    // Cycles of epsilon transitions only, like the one of ()*, add no word
+    pub fn is_finite_language(&self) -> bool {
+        let reachable = self.reachable_states();
+        let useful: HashSet<usize> = self.productive_states().intersection(&reachable).copied().collect();
+        let component = self.components(&useful);
+        !self.machine.transitions().any(|(from, _, to)| {
+            matches!((component.get(&from), component.get(&to)), (Some(a), Some(b)) if a == b)
+        })
+    }
+
+    // Strongly connected component of each state of states, only following transitions between them (Kosaraju)
+    fn components(&self, states: &HashSet<usize>) -> HashMap<usize, usize> {
+        let successors = |st: usize| -> Vec<usize> {
+            self.machine.successors(st).into_iter().filter(|to| states.contains(to)).collect()
+        };
+        let mut roots: Vec<usize> = states.iter().copied().collect();
+        roots.sort_unstable();
+
+        // States by increasing DFS finish time, with an explicit stack so that long chains do not overflow
+        let mut finished = Vec::with_capacity(states.len());
+        let mut visited = HashSet::new();
+        for root in roots {
+            if !visited.insert(root) {
+                continue;
+            }
+            let mut stack = vec![(root, successors(root))];
+            while let Some(top) = stack.len().checked_sub(1) {
+                match stack[top].1.pop() {
+                    Some(to) => {
+                        if visited.insert(to) {
+                            stack.push((to, successors(to)));
+                        }
+                    }
+                    None => {
+                        finished.push(stack[top].0);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        let mut preds: HashMap<usize, Vec<usize>> = HashMap::new();
+        for st in states.iter() {
+            for to in successors(*st) {
+                preds.entry(to).or_default().push(*st);
+            }
+        }
+        let mut component = HashMap::new();
+        for (id, root) in finished.into_iter().rev().enumerate() {
+            if component.contains_key(&root) {
+                continue;
+            }
+            let mut stack = vec![root];
+            component.insert(root, id);
+            while let Some(st) = stack.pop() {
+                for from in preds.get(&st).into_iter().flatten() {
+                    if !component.contains_key(from) {
+                        component.insert(*from, id);
+                        stack.push(*from);
+                    }
+                }
+            }
+        }
+        component
+    }
+
+    // States the automaton can be in after reading some input, found by walking symbol and epsilon transitions forward from the entries
+    pub fn reachable_states(&self) -> HashSet<usize> {
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<usize> = self.entry.iter().copied().collect();
+        while let Some(st) = stack.pop() {
+            if reachable.insert(st) {
+                stack.extend(self.machine.successors(st));
+            }
+        }
+        reachable
+    }
+
+    // Why states of a hand built automaton are useless: unreachable ones are never entered, dead ones never lead to an exit
+    // trim() removes the dead ones
+    pub fn diagnostics(&self) -> Diagnostics {
+        let reachable = self.reachable_states();
+        let productive = self.productive_states();
+        let all = 0..self.machine.nb_state();
+        Diagnostics {
+            unreachable: all.clone().filter(|st| !reachable.contains(st)).collect(),
+            dead: all.filter(|st| !productive.contains(st)).collect(),
+        }
+    }
+
+    // Removes every state from which no exit can be reached, without changing the language
+    // Entries are kept anyway, so that an automaton accepting nothing still rejects on read instead of failing with NoEntry
+    // Remaining states are renumbered in order, entry and exit sets included
+    pub fn trim(&mut self) {
+        let mut kept = self.productive_states();
+        kept.extend(self.entry.iter().copied());
+        let removed: Vec<usize> = (0..self.machine.nb_state())
+            .filter(|st| !kept.contains(st))
+            .collect();
+        for st in removed.iter().rev() {
+            // Ids come from the machine itself and are removed from the highest down
+            let _ = self.machine.remove_state(*st);
+        }
+        let renumber = |st: &usize| st - removed.partition_point(|r| r < st);
+        self.entry = self.entry.iter().map(renumber).collect();
+        self.exit = self.exit.iter().map(renumber).collect();
+        self.labels = self.labels.drain()
+            .filter(|(st, _)| kept.contains(st))
+            .map(|(st, label)| (renumber(&st), label))
             .collect();
-        self.current_states = next_states;
+        self.current_states.clear();
+    }
+
+    // States from which an exit can be reached, found by walking transitions backward from the exits
+    fn productive_states(&self) -> HashSet<usize> {
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); self.machine.nb_state()];
+        for from in 0..self.machine.nb_state() {
+            for to in self.machine.successors(from) {
+                preds[to].push(from);
+            }
+        }
+        let mut productive = HashSet::new();
+        let mut stack: Vec<usize> = self.exit.iter().copied().collect();
+        while let Some(st) = stack.pop() {
+            if productive.insert(st) {
+                stack.extend(preds[st].iter().copied());
+            }
+        }
+        productive
+    }
+
+    // Caches epsilon closures to speed up subsequent reads
+    pub fn finalize(&mut self) {
+        self.machine.finalize();
+    }
+
+    // Prepares self for many reads: drops the states that cannot reach an exit, replaces self by its DFA
+    // if determinize is set, then caches the epsilon closures
+    // Returns is_compiled(), i.e. true once reads take the fast path
+    // State ids change, so whatever was recorded on the old ones (like the capture groups of a Vregex) no longer applies
+    pub fn compile(&mut self, determinize: bool) -> bool {
+        self.trim();
+        if determinize {
+            *self = self.to_dfa();
+        }
+        self.finalize();
+        self.is_compiled()
+    }
+
+    // Whether reads use the cached epsilon closures, which a deserialized automaton or any edit drops
+    pub fn is_compiled(&self) -> bool {
+        self.machine.is_finalized()
+    }
+
+    pub fn init_for_read(&mut self) -> Result<(), AutomatonError> {
+        self.check_entries()?;
+        self.reset();
+        Ok(())
+    }
+
+    // Same as init_for_read() but reuses the current states in place instead of allocating new ones,
+    // for callers validating many short inputs in a row
+    // Entries are checked when added and when deserialized, so only a corrupted automaton could have invalid ones, which are skipped
+    pub fn reset(&mut self) {
+        self.current_states.clear();
+        let machine = &self.machine;
+        self.current_states.extend(self.entry.iter().copied().filter(|st| machine.is_valid_state_id(*st)));
+    }
+
+    pub fn read(&mut self, v: &T) {
+        self.current_states = self.machine.read_set(&self.current_states, v);
+    }
+
+    // Same as read() but returns is_alive(), so that a scan can stop as soon as nothing can match anymore
+    pub fn read_checked(&mut self, v: &T) -> bool {
+        self.read(v);
+        self.is_alive()
     }
 
     pub fn read_empty(&mut self) {
-        let next_states: HashSet<usize> = self.current_states
+        self.current_states = self.machine.closure_set(&self.current_states);
+    }
+
+    // Same as read() but returns the states reached, to follow a run step by step
+    pub fn trace_read(&mut self, v: &T) -> HashSet<usize> {
+        self.read(v);
+        self.get_current_states()
+    }
+
+    pub fn get_current_states(&self) -> HashSet<usize> {
+        self.current_states.to_hash_set()
+    }
+
+    // Adds the entry states (and their epsilon closure) to the current states,
+    // so that a new run starts at the current position while the previous ones continue
+    pub fn restart(&mut self) {
+        let entries: StateSet = self.entry.iter().copied().collect();
+        self.current_states.union_with(&self.machine.closure_set(&entries));
+    }
+
+    // False once no state is left, i.e. no further read can reach an exit
+    pub fn is_alive(&self) -> bool {
+        !self.current_states.is_empty()
+    }
+
+    // Number of current states, i.e. how much work the next read() has to do
+    pub fn nb_current_states(&self) -> usize {
+        self.current_states.len()
+    }
+
+    // Stricter than is_alive(): true iff an exit can still be reached from one of the current states
+    pub fn can_reach_exit(&self) -> bool {
+        self.productive_states().iter().any(|st| self.current_states.contains(*st))
+    }
+
+    pub fn on_exit(&self) -> bool {
+        self.is_accepting(&self.current_states)
+    }
+
+    // Takes the symbols of it and return if they are accepted, for inputs that are neither &str nor slices
+    // e.g. validate_iter("abc".chars()) or tokens coming out of a lexer
+    // Stops pulling from it once no state is left, and a malformed automaton accepts nothing
+    pub fn validate_iter<I: IntoIterator<Item = T>>(&mut self, it: I) -> bool {
+        if self.init_for_read().is_err() {
+            return false;
+        }
+        self.read_empty();
+        for v in it {
+            if !self.read_checked(&v) {
+                return false;
+            }
+        }
+        self.on_exit()
+    }
+
+    // Exit states among the current states, in increasing order
+    // Tells which branch of an alternation accepted the input when they have distinct exits
+    pub fn exits_reached(&self) -> Vec<usize> {
+        self.exits_in(&self.current_states)
+    }
+
+    // Read position over self that leaves self untouched, so several cursors can scan it at once
+    pub fn cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            automaton: self,
+            current_states: StateSet::new(),
+        }
+    }
+
+    fn start_states(&self) -> Result<StateSet, AutomatonError> {
+        self.check_entries()?;
+        Ok(self.entry.iter().copied().collect())
+    }
+
+    fn check_entries(&self) -> Result<(), AutomatonError> {
+        if self.entry.is_empty() {
+            return Err(AutomatonError::NoEntry);
+        }
+        if self.entry.iter().any(|st| !self.machine.is_valid_state_id(*st)) {
+            return Err(AutomatonError::InternalFailure);
+        }
+        Ok(())
+    }
+
+    fn is_accepting(&self, states: &StateSet) -> bool {
+        self.exit
             .iter()
-            .flat_map(|st| {
-                self.machine.apply_eps_transition(*st)
-            })
+            .any(|st| states.contains(*st))
+    }
+
+    fn exits_in(&self, states: &StateSet) -> Vec<usize> {
+        let mut exits: Vec<usize> = self.exit
+            .iter()
+            .copied()
+            .filter(|st| states.contains(*st))
             .collect();
-        self.current_states = next_states;
+        exits.sort_unstable();
+        exits
+    }
+}
+
+// Same reading API as Automaton, with the current states kept outside of the automaton
+#[derive(Debug)]
+pub struct Cursor<'a, T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    automaton: &'a Automaton<T>,
+    current_states: StateSet,
+}
+
+impl<T> Cursor<'_, T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    pub fn init_for_read(&mut self) -> Result<(), AutomatonError> {
+        self.current_states = self.automaton.start_states()?;
+        Ok(())
+    }
+
+    pub fn read(&mut self, v: &T) {
+        self.current_states = self.automaton.machine.read_set(&self.current_states, v);
+    }
+
+    // Same as Automaton::read_checked()
+    pub fn read_checked(&mut self, v: &T) -> bool {
+        self.read(v);
+        self.is_alive()
+    }
+
+    pub fn read_empty(&mut self) {
+        self.current_states = self.automaton.machine.closure_set(&self.current_states);
     }
 
     pub fn on_exit(&self) -> bool {
-        !self.current_states
-            .intersection(&self.exit)
-            .collect::<HashSet<_>>()
-            .is_empty()
+        self.automaton.is_accepting(&self.current_states)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.current_states.is_empty()
+    }
+
+    // Same as Automaton::exits_reached()
+    pub fn exits_reached(&self) -> Vec<usize> {
+        self.automaton.exits_in(&self.current_states)
+    }
+
+    // Takes seq and return if it is accepted by the automaton
+    pub fn validate_seq(&mut self, seq: &[T]) -> Result<bool, AutomatonError> {
+        self.init_for_read()?;
+        self.read_empty();
+        for v in seq.iter() {
+            if !self.read_checked(v) {
+                return Ok(false);
+            }
+        }
+        Ok(self.on_exit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vregex::Vregex;
+
+    fn accepts(autom: &mut Automaton<char>, s: &str) -> bool {
+        autom.init_for_read().unwrap();
+        autom.read_empty();
+        for c in s.chars() {
+            autom.read(&c);
+        }
+        autom.on_exit()
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut autom = Vregex::new("a*").unwrap().automaton;
+        let nb_state = autom.machine.nb_state();
+        let entry = autom.entry_iter().next().unwrap();
+        let dangling = autom.add_state();
+        autom.add_transition(entry, dangling, 'b').unwrap();
+        autom.add_eps_transition(dangling, dangling).unwrap();
+        assert_eq!(autom.machine.nb_state(), nb_state + 1);
+
+        autom.trim();
+        assert_eq!(autom.machine.nb_state(), nb_state);
+        assert_eq!(autom.nb_state(), nb_state);
+        assert!(accepts(&mut autom, ""));
+        assert!(accepts(&mut autom, "aaa"));
+        assert!(!accepts(&mut autom, "b"));
+        assert!(!accepts(&mut autom, "ab"));
+    }
+
+    #[test]
+    fn test_trim_renumbers() {
+        let mut autom = Vregex::new("ab").unwrap().automaton;
+        // Shift every state of "ab" up by one behind a dead state 0
+        let mut shifted = Automaton::new();
+        shifted.add_state();
+        let offset = shifted.absorb(&autom);
+        for st in autom.entry_iter() {
+            shifted.add_entry(st + offset).unwrap();
+            shifted.add_eps_transition(st + offset, 0).unwrap();
+        }
+        for st in autom.exit_iter() {
+            shifted.add_exit(st + offset).unwrap();
+        }
+        shifted.trim();
+        assert_eq!(shifted.machine.nb_state(), autom.machine.nb_state());
+        assert!(accepts(&mut shifted, "ab"));
+        assert!(!accepts(&mut shifted, "a"));
+        assert!(accepts(&mut autom, "ab"));
+
+        let mut empty = Vregex::new("[^a-zA-Z0-9]").unwrap().automaton;
+        empty.trim();
+        // Only the exit and the entry it cannot be reached from are left
+        assert_eq!(empty.machine.nb_state(), 2);
+        assert_eq!(empty.get_entry().len(), 1);
+        assert!(!accepts(&mut empty, ""));
+    }
+
+    #[test]
+    fn test_reachable_states() {
+        let mut autom = Vregex::new("ab").unwrap().automaton;
+        let all: HashSet<usize> = (0..autom.machine.nb_state()).collect();
+        assert_eq!(autom.reachable_states(), all);
+
+        let orphan = autom.add_state();
+        let exit = autom.exit_iter().next().unwrap();
+        autom.add_transition(orphan, exit, 'c').unwrap();
+        assert_eq!(autom.reachable_states(), all);
+        assert!(!autom.reachable_states().contains(&orphan));
+
+        assert!(Automaton::<char>::new().reachable_states().is_empty());
+    }
+
+    #[test]
+    fn test_is_deterministic() {
+        let nfa = Vregex::new("a*").unwrap().automaton;
+        assert!(!nfa.is_deterministic());
+        assert!(nfa.to_dfa().is_deterministic());
+        assert!(Vregex::new("(a+b)*c").unwrap().automaton.minimize().is_deterministic());
+
+        // Two targets for the same state and symbol
+        let mut autom: Automaton<char> = Automaton::new();
+        let st = autom.add_n_state(3);
+        autom.add_entry(st[0]).unwrap();
+        autom.add_transition(st[0], st[1], 'a').unwrap();
+        autom.add_transition(st[0], st[2], 'b').unwrap();
+        assert!(autom.is_deterministic());
+        autom.add_transition(st[0], st[2], 'a').unwrap();
+        assert!(!autom.is_deterministic());
+        // No entry or several entries
+        assert!(!Automaton::<char>::new().is_deterministic());
+        let mut two_entries: Automaton<char> = Automaton::new();
+        let st = two_entries.add_n_state(2);
+        two_entries.add_entry(st[0]).unwrap();
+        two_entries.add_entry(st[1]).unwrap();
+        assert!(!two_entries.is_deterministic());
+    }
+
+    #[test]
+    fn test_nb_state() {
+        let mut autom: Automaton<char> = Automaton::new();
+        assert_eq!(autom.nb_state(), 0);
+        autom.add_state();
+        autom.add_n_state(3);
+        assert_eq!(autom.nb_state(), 4);
+        let offset = autom.absorb(&Vregex::new("ab").unwrap().automaton);
+        assert_eq!(offset, 4);
+        assert_eq!(autom.nb_state(), autom.machine.nb_state());
+        let union = autom.union(&autom);
+        assert_eq!(union.nb_state(), 2 * autom.nb_state());
+        assert_eq!(union.nb_state(), union.machine.nb_state());
+    }
+
+    #[test]
+    fn test_labels() {
+        let autom = Vregex::new("a").unwrap().automaton;
+        let entry = autom.entry_iter().next().unwrap();
+        let exit = autom.exit_iter().next().unwrap();
+        assert_eq!(autom.label(entry), Some("literal 'a' entry"));
+        assert_eq!(autom.label(exit), Some("literal 'a' exit"));
+        assert_eq!(autom.label(autom.nb_state()), None);
+
+        // Labels follow their states through absorb() and trim()
+        let mut shifted: Automaton<char> = Automaton::new();
+        let dead = shifted.add_state();
+        shifted.set_label(dead, "dead".to_string()).unwrap();
+        let offset = shifted.absorb(&autom);
+        assert_eq!(shifted.label(entry + offset), Some("literal 'a' entry"));
+        shifted.add_entry(entry + offset).unwrap();
+        shifted.add_exit(exit + offset).unwrap();
+        shifted.trim();
+        assert_eq!(shifted.nb_state(), 2);
+        assert_eq!(shifted.label(entry), Some("literal 'a' entry"));
+        assert!(shifted.set_label(2, "missing".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_outgoing() {
+        let autom = Vregex::new("ab").unwrap().automaton;
+        let entry = autom.entry_iter().next().unwrap();
+        let (transitions, eps_targets) = autom.outgoing(entry).unwrap();
+        assert_eq!(transitions.len(), 1);
+        let (v, after_a) = transitions[0];
+        assert_eq!(v, 'a');
+        assert!(eps_targets.is_empty());
+        // The exit of a leads to the entry of b through an epsilon transition
+        let (transitions, eps_targets) = autom.outgoing(after_a).unwrap();
+        assert!(transitions.is_empty());
+        assert_eq!(eps_targets.len(), 1);
+        let (transitions, _) = autom.outgoing(eps_targets[0]).unwrap();
+        assert_eq!(transitions.iter().map(|(v, _)| *v).collect::<Vec<_>>(), vec!['b']);
+        assert!(matches!(autom.outgoing(autom.nb_state()), Err(AutomatonError::InternalFailure)));
+    }
+
+    #[test]
+    fn test_validate_iter() {
+        let mut autom = Vregex::new("ab*").unwrap().automaton;
+        assert!(autom.validate_iter("abb".chars()));
+        assert!(!autom.validate_iter("ba".chars()));
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Token {
+            Num,
+            Op,
+        }
+        // Num (Op Num)*
+        let mut builder = AutomatonBuilder::new();
+        let start = builder.state();
+        let operand = builder.state();
+        builder.entry(start).exit(operand).transition(start, operand, Token::Num).transition(operand, start, Token::Op);
+        let mut expr = builder.build().unwrap();
+        assert!(expr.validate_iter([Token::Num, Token::Op, Token::Num]));
+        assert!(!expr.validate_iter(vec![Token::Num, Token::Op]));
+        assert!(!expr.validate_iter([]));
+    }
+
+    #[test]
+    fn test_read_without_entry() {
+        // Never built, so a read fails instead of rejecting everything
+        let mut unbuilt: Automaton<char> = Automaton::new();
+        assert!(matches!(unbuilt.init_for_read(), Err(AutomatonError::NoEntry)));
+        assert!(matches!(unbuilt.cursor().validate_seq(&['a']), Err(AutomatonError::NoEntry)));
+        let st = unbuilt.add_state();
+        unbuilt.add_exit(st).unwrap();
+        assert!(matches!(unbuilt.cursor().validate_seq(&[]), Err(AutomatonError::NoEntry)));
+        // Accepting nothing is not the same, trim() and compile() keep the entry so that reads reject
+        let mut nothing = Vregex::new("[^a-zA-Z0-9]").unwrap().automaton;
+        nothing.compile(true);
+        assert!(!nothing.cursor().validate_seq(&[]).unwrap());
+        assert!(!nothing.cursor().validate_seq(&['a']).unwrap());
+    }
+
+    #[test]
+    fn test_read_checked() {
+        let mut autom = Vregex::new("abc").unwrap().automaton;
+        autom.init_for_read().unwrap();
+        autom.read_empty();
+        assert!(autom.read_checked(&'a'));
+        assert!(autom.read_checked(&'b'));
+        assert!(!autom.read_checked(&'X'));
+        assert!(!autom.on_exit());
+
+        // Nothing after the X is pulled
+        let mut pulled = 0;
+        assert!(!autom.validate_iter("abXc".chars().inspect(|_| pulled += 1)));
+        assert_eq!(pulled, 3);
+        assert!(autom.validate_iter("abc".chars()));
+        let mut cursor = autom.cursor();
+        assert!(!cursor.validate_seq(&['a', 'b', 'X']).unwrap());
+        assert!(cursor.validate_seq(&['a', 'b', 'c']).unwrap());
+    }
+
+    #[test]
+    fn test_is_finite_language() {
+        assert!(!Vregex::new("a*").unwrap().automaton.is_finite_language());
+        assert!(!Vregex::new("b(ab){2,}").unwrap().automaton.is_finite_language());
+        assert!(Vregex::new("ab").unwrap().automaton.is_finite_language());
+        assert!(Vregex::new("a{2,5}(b+c)?").unwrap().automaton.is_finite_language());
+        // Epsilon cycle
+        assert!(Vregex::new("()*").unwrap().automaton.is_finite_language());
+        // The star is on a branch that can never reach an exit
+        assert!(Vregex::new("ab+a*[^a-zA-Z0-9]").unwrap().automaton.is_finite_language());
+        assert!(Vregex::new("[^a-zA-Z0-9]").unwrap().automaton.is_finite_language());
+    }
+
+    #[test]
+    fn test_compile() {
+        let inputs = ["", "abb", "aabb", "babb", "ab", "abba", "c"];
+        let mut reference = Vregex::new("(a+b)*abb").unwrap();
+        let expected: Vec<bool> = inputs.iter().map(|s| reference.validate(s).unwrap()).collect();
+        for determinize in [false, true] {
+            let mut autom = Vregex::new("(a+b)*abb").unwrap().automaton;
+            // An unreachable state that compile() trims, adding it drops the cached closures
+            autom.add_state();
+            assert!(!autom.is_compiled());
+            assert!(autom.compile(determinize));
+            assert_eq!(autom.is_deterministic(), determinize);
+            let mut cursor = autom.cursor();
+            let got: Vec<bool> = inputs.iter()
+                .map(|s| cursor.validate_seq(&s.chars().collect::<Vec<_>>()).unwrap())
+                .collect();
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let autom = Vregex::new("ab").unwrap().automaton;
+        assert_eq!(autom.summary(), "states: 4, entries: 1, exits: 1, transitions: 2, epsilon transitions: 1, deterministic: no");
+        assert_eq!(Automaton::<char>::new().summary(), "states: 0, entries: 0, exits: 0, transitions: 0, epsilon transitions: 0, deterministic: no");
+
+        let mut dfa: Automaton<char> = Automaton::new();
+        let st = dfa.add_n_state(3);
+        dfa.add_entry(st[0]).unwrap();
+        dfa.add_exit(st[2]).unwrap();
+        dfa.add_transition(st[0], st[1], 'a').unwrap();
+        dfa.add_transition(st[1], st[2], 'b').unwrap();
+        assert_eq!(dfa.summary(), "states: 3, entries: 1, exits: 1, transitions: 2, epsilon transitions: 0, deterministic: yes");
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut autom = Vregex::new("ab").unwrap().automaton;
+        autom.init_for_read().unwrap();
+        autom.read_empty();
+        autom.read(&'a');
+        assert!(autom.is_alive());
+        // A reset drops the states of the previous run
+        autom.reset();
+        autom.read_empty();
+        autom.read(&'b');
+        assert!(!autom.is_alive());
+        autom.reset();
+        autom.read_empty();
+        for c in "ab".chars() {
+            autom.read(&c);
+        }
+        assert!(autom.on_exit());
+        autom.reset();
+        autom.read_empty();
+        assert!(!autom.on_exit());
+    }
+
+    #[test]
+    fn test_read_matches_per_state_transitions() {
+        let mut autom = Vregex::new("((ab)+c)*(z+x)*").unwrap().automaton;
+        // Worked out from the raw transitions rather than through read_set(), which read() itself uses
+        let closure = |autom: &Automaton<char>, states: HashSet<usize>| {
+            let mut reached = HashSet::new();
+            let mut stack: Vec<usize> = states.into_iter().collect();
+            while let Some(st) = stack.pop() {
+                if reached.insert(st) {
+                    stack.extend(autom.machine.eps_transitions_from(st));
+                }
+            }
+            reached
+        };
+        for word in ["ab", "abc", "cczx", "abzx", "ba", "ababcx", "zab"] {
+            autom.init_for_read().unwrap();
+            let mut expected: HashSet<usize> = autom.get_entry();
+            for c in word.chars() {
+                autom.read(&c);
+                let targets = closure(&autom, expected)
+                    .into_iter()
+                    .flat_map(|st| autom.machine.transitions_from(st).filter(|(v, _)| **v == c).map(|(_, to)| to).collect::<Vec<_>>())
+                    .collect();
+                expected = closure(&autom, targets);
+                assert_eq!(autom.current_states.to_hash_set(), expected, "{}", word);
+            }
+            let expected_exit = autom.exit_iter().any(|st| expected.contains(&st));
+            assert_eq!(autom.on_exit(), expected_exit, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_validate_seq_bytes() {
+        let mut autom: Automaton<u8> = Automaton::new();
+        let st = autom.add_n_state(4);
+        autom.add_entry(st[0]).unwrap();
+        autom.add_exit(st[3]).unwrap();
+        autom.add_transition(st[0], st[1], 1).unwrap();
+        autom.add_transition(st[1], st[2], 2).unwrap();
+        autom.add_transition(st[2], st[3], 3).unwrap();
+        autom.add_eps_transition(st[3], st[0]).unwrap();
+        let mut cursor = autom.cursor();
+        assert!(cursor.validate_seq(&[1u8, 2, 3]).unwrap());
+        assert!(cursor.validate_seq(&[1u8, 2, 3, 1, 2, 3]).unwrap());
+        assert!(!cursor.validate_seq(&[1u8, 2]).unwrap());
+        assert!(!cursor.validate_seq(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_validate_seq_tokens() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Token {
+            Number,
+            Plus,
+        }
+        // Number (Plus Number)*
+        let mut autom: Automaton<Token> = Automaton::new();
+        let st = autom.add_n_state(2);
+        autom.add_entry(st[0]).unwrap();
+        autom.add_exit(st[1]).unwrap();
+        autom.add_transition(st[0], st[1], Token::Number).unwrap();
+        autom.add_transition(st[1], st[0], Token::Plus).unwrap();
+        let mut cursor = autom.cursor();
+        assert!(cursor.validate_seq(&[Token::Number, Token::Plus, Token::Number]).unwrap());
+        assert!(!cursor.validate_seq(&[Token::Number, Token::Plus]).unwrap());
+        assert!(!cursor.validate_seq(&[Token::Plus]).unwrap());
+    }
+
+    #[test]
+    fn test_union() {
+        let ab = Vregex::new("ab").unwrap().automaton;
+        let cd = Vregex::new("cd").unwrap().automaton;
+        let mut union = ab.union(&cd);
+        assert!(accepts(&mut union, "ab"));
+        assert!(accepts(&mut union, "cd"));
+        assert!(!accepts(&mut union, "ad"));
+        assert!(!accepts(&mut union, "abcd"));
+        assert!(!accepts(&mut union, ""));
+
+        let mut with_star = union.union(&Vregex::new("e*").unwrap().automaton);
+        assert!(accepts(&mut with_star, ""));
+        assert!(accepts(&mut with_star, "eee"));
+        assert!(accepts(&mut with_star, "cd"));
+    }
+
+    #[test]
+    fn test_concat() {
+        let ab = Vregex::new("ab").unwrap().automaton;
+        let cd = Vregex::new("cd").unwrap().automaton;
+        let mut abcd = ab.concat(&cd);
+        assert!(accepts(&mut abcd, "abcd"));
+        assert!(!accepts(&mut abcd, "ab"));
+        assert!(!accepts(&mut abcd, "cd"));
+        assert!(!accepts(&mut abcd, "cdab"));
+
+        let mut starred = Vregex::new("a*").unwrap().automaton.concat(&Vregex::new("b*").unwrap().automaton);
+        assert!(accepts(&mut starred, ""));
+        assert!(accepts(&mut starred, "aab"));
+        assert!(!accepts(&mut starred, "ba"));
+    }
+
+    #[test]
+    fn test_nested_star_eps_edges() {
+        let mut regex = String::from("a");
+        for depth in 1..=5 {
+            regex = format!("({})*", regex);
+            let mut autom = Vregex::new(&regex).unwrap().automaton;
+            assert!(accepts(&mut autom, ""));
+            assert!(accepts(&mut autom, "aaa"));
+            assert!(!accepts(&mut autom, "b"));
+            // Four epsilon transitions per star and two per group
+            assert_eq!(autom.machine.eps_transitions().count(), 6 * depth, "{}", regex);
+            // The entry reaches the bounds of every star and group and the start of a, nothing more,
+            // except the end of (a) which needs an a
+            let entry = autom.entry_iter().next().unwrap();
+            assert_eq!(autom.machine.apply_eps_transition(entry).len(), 4 * depth, "{}", regex);
+        }
+    }
+
+    #[test]
+    fn test_star() {
+        let ab = Vregex::new("ab").unwrap().automaton;
+        let nb_state = ab.machine.nb_state();
+        let mut star = ab.star();
+        for word in ["", "ab", "abab", "ababab"] {
+            assert!(accepts(&mut star, word), "{}", word);
+        }
+        for word in ["a", "aba", "ba", "abb"] {
+            assert!(!accepts(&mut star, word), "{}", word);
+        }
+        // self is left untouched
+        let mut ab = ab;
+        assert_eq!(ab.machine.nb_state(), nb_state);
+        assert!(accepts(&mut ab, "ab"));
+        assert!(!accepts(&mut ab, ""));
+        assert!(!accepts(&mut ab, "abab"));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let ab_star = Vregex::new("(ab)*").unwrap().automaton;
+        let a_any = Vregex::new("a.*").unwrap().automaton;
+        let mut inter = ab_star.intersect(&a_any);
+        assert!(accepts(&mut inter, "ab"));
+        assert!(accepts(&mut inter, "abab"));
+        assert!(!accepts(&mut inter, ""));
+        assert!(!accepts(&mut inter, "a"));
+        assert!(!accepts(&mut inter, "aba"));
+        assert!(!accepts(&mut inter, "ac"));
+
+        let mut disjoint = Vregex::new("a*").unwrap().automaton.intersect(&Vregex::new("b+c").unwrap().automaton);
+        assert!(disjoint.is_empty_language());
+        assert!(!accepts(&mut disjoint, ""));
+    }
+
+    #[test]
+    fn test_reverse() {
+        let ab = Vregex::new("ab").unwrap().automaton;
+        let mut ba = ab.reverse();
+        assert!(accepts(&mut ba, "ba"));
+        assert!(!accepts(&mut ba, "ab"));
+
+        let mut autom = Vregex::new("(a+bc)*d?").unwrap().automaton;
+        let mut twice = autom.reverse().reverse();
+        for word in ["", "a", "bc", "abca", "aad", "cb", "da", "b"] {
+            assert_eq!(accepts(&mut twice, word), accepts(&mut autom, word), "{}", word);
+        }
+        let mut reversed = autom.reverse();
+        assert!(accepts(&mut reversed, "dacb"));
+        assert!(!accepts(&mut reversed, "abcd"));
+    }
+
+    #[test]
+    fn test_map_symbols() {
+        let mut chars = Vregex::new("[a-z]*0").unwrap().automaton;
+        let mut bytes = chars.map_symbols(|c| *c as u8);
+        for word in ["", "0", "abc0", "zz0", "ab", "a0b", "A0"] {
+            assert_eq!(bytes.validate_iter(word.bytes()), accepts(&mut chars, word), "{}", word);
+        }
+
+        let mut folded = Vregex::new("ab").unwrap().automaton.map_symbols(|c| c.to_ascii_uppercase());
+        assert!(accepts(&mut folded, "AB"));
+        assert!(!accepts(&mut folded, "ab"));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        // 0 -a-> 1 -b-> 2 (exit), 1 -c-> 3 dead end, 4 -a-> 2 never entered
+        let mut autom: Automaton<char> = Automaton::new();
+        let st = autom.add_n_state(5);
+        autom.add_entry(st[0]).unwrap();
+        autom.add_exit(st[2]).unwrap();
+        autom.add_transition(st[0], st[1], 'a').unwrap();
+        autom.add_transition(st[1], st[2], 'b').unwrap();
+        autom.add_transition(st[1], st[3], 'c').unwrap();
+        autom.add_transition(st[4], st[2], 'a').unwrap();
+        let diagnostics = autom.diagnostics();
+        assert_eq!(diagnostics.unreachable, HashSet::from([st[4]]));
+        assert_eq!(diagnostics.dead, HashSet::from([st[3]]));
+
+        let clean = Vregex::new("ab*").unwrap().automaton.diagnostics();
+        assert!(clean.unreachable.is_empty());
+        assert!(clean.dead.is_empty());
+    }
+
+    #[test]
+    fn test_is_empty_language() {
+        let mut autom: Automaton<char> = Automaton::new();
+        assert!(autom.is_empty_language());
+        let st = autom.add_n_state(4);
+        autom.add_entry(st[0]).unwrap();
+        autom.add_transition(st[0], st[1], 'a').unwrap();
+        autom.add_eps_transition(st[1], st[2]).unwrap();
+        // No exit at all
+        assert!(autom.is_empty_language());
+        // Exit not reachable from the entry
+        autom.add_transition(st[3], st[2], 'b').unwrap();
+        autom.add_exit(st[3]).unwrap();
+        assert!(autom.is_empty_language());
+        // Exit reachable through a symbol then an epsilon transition
+        autom.add_exit(st[2]).unwrap();
+        assert!(!autom.is_empty_language());
     }
 }
@@ -0,0 +1,120 @@
+use super::{Automaton, AutomatonError};
+
+// Compact encoding of a char automaton, faster to load than serde JSON or than parsing the regex again
+// Every number is a little-endian u32: the number of states, the entries and the exits (each as a count followed by ids),
+// then for every state its transitions (a count followed by symbol and target pairs) and its epsilon targets
+// (a count followed by ids), sorted so that the same automaton always gives the same bytes
+// Automata of more than u32::MAX states are not supported
+// Labels are only meant for debugging and are not kept
+impl Automaton<char> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut push = |n: usize| out.extend_from_slice(&(n as u32).to_le_bytes());
+        push(self.machine.nb_state());
+        for set in [&self.entry, &self.exit] {
+            let mut ids: Vec<usize> = set.iter().copied().collect();
+            ids.sort_unstable();
+            push(ids.len());
+            ids.into_iter().for_each(&mut push);
+        }
+        for st in 0..self.machine.nb_state() {
+            let mut transitions: Vec<(char, usize)> = self.machine.transitions_from(st).map(|(v, to)| (*v, to)).collect();
+            transitions.sort_unstable();
+            push(transitions.len());
+            for (v, to) in transitions {
+                push(v as usize);
+                push(to);
+            }
+            let mut eps_targets: Vec<usize> = self.machine.eps_transitions_from(st).collect();
+            eps_targets.sort_unstable();
+            push(eps_targets.len());
+            eps_targets.into_iter().for_each(&mut push);
+        }
+        out
+    }
+
+    // Reads back the output of to_bytes(), finalized so that it is ready for reads
+    // Fails with InvalidEncoding if bytes is truncated, has trailing bytes, or holds an unknown state id or an invalid char
+    pub fn from_bytes(bytes: &[u8]) -> Result<Automaton<char>, AutomatonError> {
+        let mut reader = Reader { bytes };
+        let nb_state = reader.next()?;
+        // Each state takes at least its two counts, which bounds what a corrupted count can make us allocate
+        if nb_state > bytes.len() / 8 {
+            return Err(AutomatonError::InvalidEncoding);
+        }
+        let mut out = Automaton::new();
+        out.add_n_state(nb_state);
+        let invalid = |_| AutomatonError::InvalidEncoding;
+        for _ in 0..reader.next()? {
+            out.add_entry(reader.next()?).map_err(invalid)?;
+        }
+        for _ in 0..reader.next()? {
+            out.add_exit(reader.next()?).map_err(invalid)?;
+        }
+        for from in 0..nb_state {
+            for _ in 0..reader.next()? {
+                let v = char::from_u32(reader.next()? as u32).ok_or(AutomatonError::InvalidEncoding)?;
+                out.add_transition(from, reader.next()?, v).map_err(invalid)?;
+            }
+            for _ in 0..reader.next()? {
+                out.add_eps_transition(from, reader.next()?).map_err(invalid)?;
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(AutomatonError::InvalidEncoding);
+        }
+        out.finalize();
+        Ok(out)
+    }
+}
+
+// Numbers left to decode in from_bytes()
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn next(&mut self) -> Result<usize, AutomatonError> {
+        let (number, rest) = self.bytes.split_first_chunk::<4>().ok_or(AutomatonError::InvalidEncoding)?;
+        self.bytes = rest;
+        Ok(u32::from_le_bytes(*number) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vregex::Vregex;
+
+    #[test]
+    fn test_round_trip() {
+        let autom = Vregex::new("(a+b)*c[x-z]?").unwrap().automaton;
+        let bytes = autom.to_bytes();
+        let mut loaded = Automaton::from_bytes(&bytes).unwrap();
+        assert!(loaded.is_compiled());
+        assert_eq!(loaded.nb_state(), autom.nb_state());
+        assert!(loaded.equivalent(&autom));
+        assert!(loaded.validate_iter("abacy".chars()));
+        assert!(!loaded.validate_iter("ab".chars()));
+        assert_eq!(loaded.to_bytes(), bytes);
+
+        let empty: Automaton<char> = Automaton::new();
+        assert!(Automaton::from_bytes(&empty.to_bytes()).unwrap().is_empty_language());
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let bytes = Vregex::new("ab*").unwrap().automaton.to_bytes();
+        for len in 0..bytes.len() {
+            assert!(matches!(Automaton::from_bytes(&bytes[..len]), Err(AutomatonError::InvalidEncoding)), "{}", len);
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(Automaton::from_bytes(&trailing), Err(AutomatonError::InvalidEncoding)));
+        // A huge state count, then an entry out of range
+        assert!(matches!(Automaton::from_bytes(&[0xff; 8]), Err(AutomatonError::InvalidEncoding)));
+        let mut bad_entry = bytes.clone();
+        bad_entry[8..12].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(matches!(Automaton::from_bytes(&bad_entry), Err(AutomatonError::InvalidEncoding)));
+    }
+}
@@ -0,0 +1,147 @@
+use super::{Automaton, AutomatonError};
+
+// State of an AutomatonBuilder, only obtained from AutomatonBuilder::state()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateId(usize);
+
+impl StateId {
+    // Id of the state in the built automaton
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+// Records states and transitions to hand build an Automaton, e.g.
+// let mut b = AutomatonBuilder::new(); let s = b.state(); let t = b.state(); b.entry(s).exit(t).transition(s, t, 'a');
+// Using a StateId of another builder is only detected by build()
+#[derive(Debug)]
+pub struct AutomatonBuilder<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    nb_state: usize,
+    entries: Vec<StateId>,
+    exits: Vec<StateId>,
+    transitions: Vec<(StateId, StateId, T)>,
+    eps_transitions: Vec<(StateId, StateId)>,
+}
+
+impl<T> Default for AutomatonBuilder<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    fn default() -> Self {
+        AutomatonBuilder::new()
+    }
+}
+
+impl<T> AutomatonBuilder<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    pub fn new() -> AutomatonBuilder<T> {
+        AutomatonBuilder {
+            nb_state: 0,
+            entries: Vec::new(),
+            exits: Vec::new(),
+            transitions: Vec::new(),
+            eps_transitions: Vec::new(),
+        }
+    }
+
+    pub fn state(&mut self) -> StateId {
+        self.nb_state += 1;
+        StateId(self.nb_state - 1)
+    }
+
+    pub fn transition(&mut self, from: StateId, to: StateId, v: T) -> &mut Self {
+        self.transitions.push((from, to, v));
+        self
+    }
+
+    pub fn epsilon(&mut self, from: StateId, to: StateId) -> &mut Self {
+        self.eps_transitions.push((from, to));
+        self
+    }
+
+    pub fn entry(&mut self, st: StateId) -> &mut Self {
+        self.entries.push(st);
+        self
+    }
+
+    pub fn exit(&mut self, st: StateId) -> &mut Self {
+        self.exits.push(st);
+        self
+    }
+
+    // Fails with InternalFailure if a StateId does not come from this builder
+    pub fn build(&self) -> Result<Automaton<T>, AutomatonError> {
+        let mut out = Automaton::new();
+        out.add_n_state(self.nb_state);
+        for st in self.entries.iter() {
+            out.add_entry(st.0)?;
+        }
+        for st in self.exits.iter() {
+            out.add_exit(st.0)?;
+        }
+        for (from, to, v) in self.transitions.iter() {
+            out.add_transition(from.0, to.0, *v)?;
+        }
+        for (from, to) in self.eps_transitions.iter() {
+            out.add_eps_transition(from.0, to.0)?;
+        }
+        out.finalize();
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_ab() {
+        let mut builder = AutomatonBuilder::new();
+        let start = builder.state();
+        let middle = builder.state();
+        let end = builder.state();
+        builder
+            .entry(start)
+            .exit(end)
+            .transition(start, middle, 'a')
+            .transition(middle, end, 'b');
+        let autom = builder.build().unwrap();
+        assert_eq!(autom.nb_state(), 3);
+        assert_eq!(end.index(), 2);
+        let mut cursor = autom.cursor();
+        assert!(cursor.validate_seq(&['a', 'b']).unwrap());
+        assert!(!cursor.validate_seq(&['a']).unwrap());
+        assert!(!cursor.validate_seq(&['b', 'a']).unwrap());
+        assert!(autom.equivalent(&crate::vregex::Vregex::new("ab").unwrap().automaton));
+    }
+
+    #[test]
+    fn test_build_epsilon() {
+        // a*
+        let mut builder = AutomatonBuilder::new();
+        let st = builder.state();
+        let after = builder.state();
+        builder.entry(st).exit(st).transition(st, after, 'a').epsilon(after, st);
+        let autom = builder.build().unwrap();
+        let mut cursor = autom.cursor();
+        assert!(cursor.validate_seq(&[]).unwrap());
+        assert!(cursor.validate_seq(&['a', 'a']).unwrap());
+        assert!(!cursor.validate_seq(&['b']).unwrap());
+    }
+
+    #[test]
+    fn test_foreign_state_id() {
+        let mut big: AutomatonBuilder<char> = AutomatonBuilder::new();
+        big.state();
+        let foreign = big.state();
+        let mut small: AutomatonBuilder<char> = AutomatonBuilder::new();
+        let st = small.state();
+        small.entry(st).transition(st, foreign, 'a');
+        assert!(matches!(small.build(), Err(AutomatonError::InternalFailure)));
+    }
+}
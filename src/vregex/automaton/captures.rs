@@ -0,0 +1,116 @@
+use super::state_set::StateSet;
+use super::Automaton;
+use std::collections::HashMap;
+
+// Group boundary carried by a state, see Automaton::captures()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupTag {
+    Open(usize),
+    Close(usize),
+}
+
+// Start and end offset of every group along one path
+type Slots = Vec<(Option<usize>, Option<usize>)>;
+
+impl<T> Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    // Anchored read of input keeping track of capture groups, in the style of a Pike VM:
+    // every live state carries the group offsets of the path that reached it, and a path going through
+    // a state tagged in tags records the current offset as the start or the end of that group
+    // When several paths reach the same state the first one wins, states being tried in increasing id order,
+    // so an ambiguous group gets a deterministic but unspecified span
+    // input yields each symbol with the offset right after it, start being the offset before the first one
+    // Returns None if input is rejected, otherwise nb_groups + 1 spans where index 0 is the whole input
+    // and a group that took no part in the match is None
+    pub fn captures<I>(&self, start: usize, input: I, tags: &HashMap<usize, GroupTag>, nb_groups: usize) -> Option<Vec<Option<(usize, usize)>>>
+    where
+        I: IntoIterator<Item = (T, usize)>,
+    {
+        let mut seen = StateSet::new();
+        let mut threads = Vec::new();
+        let mut entries: Vec<usize> = self.entry.iter().copied().collect();
+        entries.sort_unstable();
+        for st in entries {
+            self.add_thread(st, vec![(None, None); nb_groups + 1], start, tags, &mut seen, &mut threads);
+        }
+
+        let mut end = start;
+        for (v, offset) in input {
+            seen.clear();
+            let mut next = Vec::new();
+            for (st, slots) in threads.iter() {
+                let mut targets: Vec<usize> = self.machine.transitions_from(*st)
+                    .filter(|(w, _)| **w == v)
+                    .map(|(_, to)| to)
+                    .collect();
+                targets.sort_unstable();
+                for to in targets {
+                    self.add_thread(to, slots.clone(), offset, tags, &mut seen, &mut next);
+                }
+            }
+            if next.is_empty() {
+                return None;
+            }
+            threads = next;
+            end = offset;
+        }
+
+        let (_, slots) = threads.into_iter().find(|(st, _)| self.exit.contains(st))?;
+        let mut spans: Vec<Option<(usize, usize)>> = slots.into_iter()
+            .map(|slot| match slot {
+                (Some(open), Some(close)) => Some((open, close)),
+                _ => None,
+            })
+            .collect();
+        spans[0] = Some((start, end));
+        Some(spans)
+    }
+
+    // Pushes st and every state reachable from it through epsilon transitions, unless already seen at this offset,
+    // recording offset in the slots of the groups opened or closed on the way
+    fn add_thread(&self, st: usize, slots: Slots, offset: usize, tags: &HashMap<usize, GroupTag>, seen: &mut StateSet, threads: &mut Vec<(usize, Slots)>) {
+        let mut stack = vec![(st, slots)];
+        while let Some((st, mut slots)) = stack.pop() {
+            if !seen.insert(st) {
+                continue;
+            }
+            match tags.get(&st) {
+                Some(GroupTag::Open(group)) => {
+                    if let Some(slot) = slots.get_mut(*group) {
+                        *slot = (Some(offset), None);
+                    }
+                }
+                Some(GroupTag::Close(group)) => {
+                    if let Some(slot) = slots.get_mut(*group) {
+                        slot.1 = Some(offset);
+                    }
+                }
+                None => {}
+            }
+            let mut targets: Vec<usize> = self.machine.eps_transitions_from(st).collect();
+            // Popped in increasing order
+            targets.sort_unstable_by(|a, b| b.cmp(a));
+            stack.extend(targets.into_iter().map(|to| (to, slots.clone())));
+            threads.push((st, slots));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vregex::Vregex;
+
+    #[test]
+    fn test_ambiguous_groups_are_deterministic() {
+        let mut vrg = Vregex::new("(a*)(a*)").unwrap();
+        let first = vrg.captures("aaa").unwrap();
+        let (_, middle) = first[1].unwrap();
+        assert_eq!(first[0], Some((0, 3)));
+        assert_eq!(first[2], Some((middle, 3)));
+        for _ in 0..10 {
+            assert_eq!(Vregex::new("(a*)(a*)").unwrap().captures("aaa").unwrap(), first);
+        }
+    }
+}
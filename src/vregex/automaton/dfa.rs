@@ -0,0 +1,330 @@
+use super::Automaton;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+impl<T> Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    // Subset construction: each state of the result is the epsilon closure of a set of states of self
+    // The result has a single entry, no epsilon transition and at most one target per (state, symbol)
+    pub fn to_dfa(&self) -> Automaton<T> {
+        let alphabet: Vec<T> = self.machine.symbols().into_iter().copied().collect();
+        let mut dfa = Automaton::new();
+        let start: BTreeSet<usize> = self.entry
+            .iter()
+            .flat_map(|st| self.machine.apply_eps_transition(*st))
+            .collect();
+        let mut ids: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        let start_id = dfa.add_state();
+        dfa.entry.insert(start_id);
+        ids.insert(start.clone(), start_id);
+        let mut todo = vec![start];
+
+        while let Some(set) = todo.pop() {
+            let from = ids[&set];
+            if set.iter().any(|st| self.exit.contains(st)) {
+                dfa.exit.insert(from);
+            }
+            for v in alphabet.iter() {
+                let next: BTreeSet<usize> = set
+                    .iter()
+                    .flat_map(|st| self.machine.apply_transition(*st, v))
+                    .collect();
+                if next.is_empty() {
+                    continue;
+                }
+                let to = match ids.get(&next) {
+                    Some(id) => *id,
+                    None => {
+                        let id = dfa.add_state();
+                        ids.insert(next.clone(), id);
+                        todo.push(next);
+                        id
+                    }
+                };
+                // Both ids come from add_state so this cannot fail
+                let _ = dfa.add_transition(from, to, *v);
+            }
+        }
+        dfa
+    }
+
+    // Determinizes self then merges equivalent states with Hopcroft's partition refinement
+    // States that cannot reach an exit are dropped, so the result is the minimal (partial) DFA
+    pub fn minimize(&self) -> Automaton<T> {
+        let dfa = self.to_dfa();
+        let alphabet: Vec<T> = dfa.machine.symbols().into_iter().copied().collect();
+        let nb_state = dfa.machine.nb_state();
+        // Missing transitions go to an implicit dead state of id nb_state
+        let dead = nb_state;
+        let mut delta = vec![vec![dead; alphabet.len()]; nb_state + 1];
+        for (st, row) in delta.iter_mut().enumerate().take(nb_state) {
+            for (i, v) in alphabet.iter().enumerate() {
+                if let Some(to) = dfa.machine.apply_transition(st, v).into_iter().next() {
+                    row[i] = to;
+                }
+            }
+        }
+        let mut preds: Vec<Vec<Vec<usize>>> = vec![vec![Vec::new(); nb_state + 1]; alphabet.len()];
+        for (st, row) in delta.iter().enumerate() {
+            for (i, to) in row.iter().enumerate() {
+                preds[i][*to].push(st);
+            }
+        }
+
+        let finals: BTreeSet<usize> = dfa.exit.iter().copied().collect();
+        let others: BTreeSet<usize> = (0..=nb_state).filter(|st| !finals.contains(st)).collect();
+        let mut partition: Vec<BTreeSet<usize>> = vec![finals.clone(), others];
+        partition.retain(|block| !block.is_empty());
+        let mut work: Vec<BTreeSet<usize>> = partition.clone();
+
+        while let Some(splitter) = work.pop() {
+            for pred in preds.iter() {
+                let x: HashSet<usize> = splitter.iter().flat_map(|st| pred[*st].iter().copied()).collect();
+                if x.is_empty() {
+                    continue;
+                }
+                let mut refined = Vec::with_capacity(partition.len());
+                for block in partition.into_iter() {
+                    let (inside, outside): (BTreeSet<usize>, BTreeSet<usize>) =
+                        block.iter().partition(|st| x.contains(st));
+                    if inside.is_empty() || outside.is_empty() {
+                        refined.push(block);
+                        continue;
+                    }
+                    if let Some(pos) = work.iter().position(|w| *w == block) {
+                        work.swap_remove(pos);
+                        work.push(inside.clone());
+                        work.push(outside.clone());
+                    } else if inside.len() <= outside.len() {
+                        work.push(inside.clone());
+                    } else {
+                        work.push(outside.clone());
+                    }
+                    refined.push(inside);
+                    refined.push(outside);
+                }
+                partition = refined;
+            }
+        }
+
+        let mut block_of = vec![0; nb_state + 1];
+        for (b, block) in partition.iter().enumerate() {
+            for st in block.iter() {
+                block_of[*st] = b;
+            }
+        }
+        let dead_block = block_of[dead];
+        let entry_block = dfa.entry.iter().map(|st| block_of[*st]).next().unwrap_or(dead_block);
+
+        let mut out = Automaton::new();
+        let mut ids: HashMap<usize, usize> = HashMap::new();
+        for (b, block) in partition.iter().enumerate() {
+            if b != dead_block || b == entry_block {
+                ids.insert(b, out.add_state());
+            }
+            if block.iter().any(|st| finals.contains(st)) {
+                out.exit.insert(ids[&b]);
+            }
+        }
+        out.entry.insert(ids[&entry_block]);
+        for (b, block) in partition.iter().enumerate() {
+            if b == dead_block {
+                continue;
+            }
+            let repr = *block.iter().next().unwrap();
+            for (i, v) in alphabet.iter().enumerate() {
+                let to_block = block_of[delta[repr][i]];
+                if to_block != dead_block {
+                    // Both ids come from add_state so this cannot fail
+                    let _ = out.add_transition(ids[&b], ids[&to_block], *v);
+                }
+            }
+        }
+        out
+    }
+
+    // Automaton accepting the words over alphabet that self rejects
+    // self is determinized then completed with a trap state before swapping exit and non-exit states
+    // Transitions on symbols outside of alphabet are dropped, so words using them are rejected
+    pub fn complement(&self, alphabet: &HashSet<T>) -> Automaton<T> {
+        let dfa = self.to_dfa();
+        let nb_state = dfa.machine.nb_state();
+        let mut out = Automaton::new();
+        out.add_n_state(nb_state);
+        let trap = out.add_state();
+        out.entry = dfa.entry.clone();
+        for st in 0..=nb_state {
+            if !dfa.exit.contains(&st) {
+                out.exit.insert(st);
+            }
+            for v in alphabet.iter() {
+                let to = if st == trap {
+                    trap
+                } else {
+                    dfa.dfa_step(st, v).unwrap_or(trap)
+                };
+                // Both ids come from add_state so this cannot fail
+                let _ = out.add_transition(st, to, *v);
+            }
+        }
+        out
+    }
+
+    // Automaton accepting the words accepted by self and rejected by other, i.e. self ∩ complement(other)
+    // As with complement(), alphabet must hold every symbol of the words of self that should be kept:
+    // a word using a symbol outside of it is dropped even if other rejects it
+    pub fn difference(&self, other: &Automaton<T>, alphabet: &HashSet<T>) -> Automaton<T> {
+        self.intersect(&other.complement(alphabet))
+    }
+
+    // True iff self and other accept the same words
+    // Walks the product of both DFAs and looks for a pair of states where only one side accepts
+    pub fn equivalent(&self, other: &Automaton<T>) -> bool {
+        let left = self.to_dfa();
+        let right = other.to_dfa();
+        let mut alphabet: HashSet<T> = left.machine.symbols().into_iter().copied().collect();
+        alphabet.extend(right.machine.symbols().into_iter().copied());
+
+        // None stands for the dead state of a partial DFA
+        let start = (left.entry.iter().next().copied(), right.entry.iter().next().copied());
+        let mut seen = HashSet::new();
+        seen.insert(start);
+        let mut todo = vec![start];
+        while let Some((l, r)) = todo.pop() {
+            let l_accepts = l.is_some_and(|st| left.exit.contains(&st));
+            let r_accepts = r.is_some_and(|st| right.exit.contains(&st));
+            if l_accepts != r_accepts {
+                return false;
+            }
+            for v in alphabet.iter() {
+                let next = (l.and_then(|st| left.dfa_step(st, v)), r.and_then(|st| right.dfa_step(st, v)));
+                if seen.insert(next) {
+                    todo.push(next);
+                }
+            }
+        }
+        true
+    }
+
+    // Target of the transition on v from st, assuming self is deterministic
+    fn dfa_step(&self, st: usize, v: &T) -> Option<usize> {
+        self.machine.apply_transition(st, v).into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vregex::Vregex;
+
+    // Every word of length <= max_len over alphabet
+    fn words(alphabet: &[char], max_len: usize) -> Vec<String> {
+        let mut out = vec![String::new()];
+        let mut last = vec![String::new()];
+        for _ in 0..max_len {
+            last = last
+                .iter()
+                .flat_map(|w| alphabet.iter().map(move |c| format!("{}{}", w, c)))
+                .collect();
+            out.extend(last.iter().cloned());
+        }
+        out
+    }
+
+    #[test]
+    fn test_to_dfa_same_language() {
+        let mut nfa = Vregex::new("((ab)+c)*(z+x)*").unwrap();
+        let mut dfa = Vregex::new("((ab)+c)*(z+x)*").unwrap();
+        dfa.automaton = nfa.automaton.to_dfa();
+        for w in words(&['a', 'b', 'c', 'z', 'x'], 5) {
+            assert_eq!(nfa.validate(&w).unwrap(), dfa.validate(&w).unwrap(), "{}", w);
+        }
+        assert!(dfa.validate("ababzx").unwrap());
+        assert!(!dfa.validate("ba").unwrap());
+    }
+
+    #[test]
+    fn test_minimize_star() {
+        let mut vrg = Vregex::new("a*").unwrap();
+        let min = vrg.automaton.minimize();
+        assert_eq!(min.machine.nb_state(), 1);
+        vrg.automaton = min;
+        assert!(vrg.validate("").unwrap());
+        assert!(vrg.validate("aaaa").unwrap());
+        assert!(!vrg.validate("ab").unwrap());
+    }
+
+    #[test]
+    fn test_minimize_same_language() {
+        for regex in ["((ab)+c)*(z+x)*", "(a+b)*abb", "a(b+c)d?"] {
+            let mut nfa = Vregex::new(regex).unwrap();
+            let mut min = Vregex::new(regex).unwrap();
+            min.automaton = nfa.automaton.minimize();
+            for w in words(&['a', 'b', 'c', 'd', 'z', 'x'], 4) {
+                assert_eq!(nfa.validate(&w).unwrap(), min.validate(&w).unwrap(), "{} on {}", regex, w);
+            }
+        }
+        // The classic (a+b)*abb example has 4 states once minimal
+        assert_eq!(Vregex::new("(a+b)*abb").unwrap().automaton.minimize().machine.nb_state(), 4);
+        // Equivalent regexes minimize to the same number of states
+        assert_eq!(
+            Vregex::new("(a+b)*").unwrap().automaton.minimize().machine.nb_state(),
+            Vregex::new("(a*b*)*").unwrap().automaton.minimize().machine.nb_state(),
+        );
+    }
+
+    #[test]
+    fn test_minimize_empty_language() {
+        let mut vrg = Vregex::new("[^a-zA-Z0-9]").unwrap();
+        vrg.automaton = vrg.automaton.minimize();
+        assert_eq!(vrg.automaton.machine.nb_state(), 1);
+        assert!(!vrg.validate("").unwrap());
+        assert!(!vrg.validate("a").unwrap());
+    }
+
+    #[test]
+    fn test_difference() {
+        let left = Vregex::new("a*b?").unwrap().automaton;
+        let right = Vregex::new("a*").unwrap().automaton;
+        let mut vrg = Vregex::new("z").unwrap();
+        vrg.automaton = left.difference(&right, &['a', 'b'].into_iter().collect());
+        assert!(vrg.validate("b").unwrap());
+        assert!(vrg.validate("aab").unwrap());
+        assert!(!vrg.validate("aa").unwrap());
+        assert!(!vrg.validate("").unwrap());
+        // b is outside of the alphabet, so every word using it is lost
+        vrg.automaton = left.difference(&right, &['a'].into_iter().collect());
+        assert!(vrg.automaton.is_empty_language());
+    }
+
+    #[test]
+    fn test_complement() {
+        let mut vrg = Vregex::new("a").unwrap();
+        vrg.automaton = vrg.automaton.complement(&['a', 'b'].into_iter().collect());
+        assert!(!vrg.validate("a").unwrap());
+        assert!(vrg.validate("b").unwrap());
+        assert!(vrg.validate("").unwrap());
+        assert!(vrg.validate("aa").unwrap());
+        assert!(vrg.validate("abba").unwrap());
+        assert!(!vrg.validate("c").unwrap());
+
+        let mut vrg = Vregex::new("(a+b)*").unwrap();
+        vrg.automaton = vrg.automaton.complement(&['a', 'b'].into_iter().collect());
+        assert!(vrg.automaton.is_empty_language());
+    }
+
+    #[test]
+    fn test_to_dfa_is_deterministic() {
+        let nfa = Vregex::new("(a+ab)*b").unwrap();
+        let dfa = nfa.automaton.to_dfa();
+        assert_eq!(dfa.get_entry().len(), 1);
+        for st in 0..dfa.machine.nb_state() {
+            assert!(dfa.machine.apply_eps_transition(st).len() == 1);
+            for v in ['a', 'b'] {
+                assert!(dfa.machine.apply_transition(st, &v).len() <= 1);
+            }
+        }
+    }
+}
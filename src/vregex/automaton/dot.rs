@@ -0,0 +1,107 @@
+use super::Automaton;
+use std::fmt::Write;
+
+impl<T> Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    // GraphViz digraph of the automaton, to be rendered with e.g. dot -Tpng
+    // Exits are double circles, entries have an incoming arrow from a point
+    // and epsilon transitions are dashed, labeled states show their label next to them
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        out.push_str("digraph automaton {\n");
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [shape=circle];\n");
+        for st in 0..self.machine.nb_state() {
+            let mut attributes = Vec::new();
+            if self.exit.contains(&st) {
+                attributes.push("shape=doublecircle".to_string());
+            }
+            if let Some(label) = self.label(st) {
+                attributes.push(format!("xlabel=\"{}\"", escape_label(label)));
+            }
+            if attributes.is_empty() {
+                let _ = writeln!(out, "    {};", st);
+            } else {
+                let _ = writeln!(out, "    {} [{}];", st, attributes.join(", "));
+            }
+        }
+        // States and transitions live in hash based collections, they are sorted so that the output is reproducible
+        let mut entries: Vec<usize> = self.entry.iter().copied().collect();
+        entries.sort_unstable();
+        for st in entries {
+            let _ = writeln!(out, "    entry{} [shape=point];", st);
+            let _ = writeln!(out, "    entry{} -> {};", st, st);
+        }
+        let mut transitions: Vec<(usize, usize, String)> = self.machine.transitions()
+            .map(|(from, v, to)| (from, to, escape_label(&format!("{:?}", v))))
+            .collect();
+        transitions.sort_unstable();
+        for (from, to, label) in transitions {
+            let _ = writeln!(out, "    {} -> {} [label=\"{}\"];", from, to, label);
+        }
+        let mut eps_transitions: Vec<(usize, usize)> = self.machine.eps_transitions().collect();
+        eps_transitions.sort_unstable();
+        for (from, to) in eps_transitions {
+            let _ = writeln!(out, "    {} -> {} [style=dashed, label=\"ε\"];", from, to);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+// Makes s usable inside a double-quoted DOT string
+fn escape_label(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vregex::Vregex;
+
+    #[test]
+    fn test_to_dot() {
+        let autom = Vregex::new("a*b").unwrap().automaton;
+        let dot = autom.to_dot();
+        assert!(dot.starts_with("digraph automaton {\n"));
+        assert!(dot.ends_with("}\n"));
+        let nb_state = autom.machine.nb_state();
+        let node_lines = dot.lines()
+            .filter(|l| l.trim_start().chars().next().is_some_and(|c| c.is_ascii_digit()) && !l.contains("->"))
+            .count();
+        assert_eq!(node_lines, nb_state);
+        assert_eq!(dot.matches("[label=\"").count(), autom.machine.transitions().count());
+        assert_eq!(dot.matches("style=dashed").count(), autom.machine.eps_transitions().count());
+        assert_eq!(dot.matches("shape=doublecircle").count(), autom.get_exit().len());
+        assert_eq!(dot.matches("[shape=point]").count(), autom.get_entry().len());
+        assert!(dot.contains("[label=\"'a'\"]"));
+        assert!(dot.contains("[label=\"'b'\"]"));
+        assert!(dot.contains("xlabel=\"literal 'a' entry\""));
+    }
+
+    #[test]
+    fn test_to_dot_is_reproducible() {
+        // Each build gets new hashing seeds
+        let dot = Vregex::new("(a+b+[c-f])*ab").unwrap().automaton.to_dot();
+        for _ in 0..5 {
+            assert_eq!(Vregex::new("(a+b+[c-f])*ab").unwrap().automaton.to_dot(), dot);
+        }
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("'\"'"), "'\\\"'");
+        assert_eq!(escape_label("'\\\\'"), "'\\\\\\\\'");
+    }
+}
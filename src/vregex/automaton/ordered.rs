@@ -0,0 +1,37 @@
+// Serializers writing hash based collections in a fixed order, so that serializing the same automaton
+// always gives the same output whatever the hashing seed
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// As an increasing sequence, which deserializes back into a HashSet
+pub fn sorted_set<S: Serializer>(set: &HashSet<usize>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut ids: Vec<usize> = set.iter().copied().collect();
+    ids.sort_unstable();
+    ids.serialize(serializer)
+}
+
+pub fn sorted_labels<S: Serializer>(labels: &HashMap<usize, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    labels.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+// Symbols need not be Ord, so entries are ordered by the Debug form of their symbol
+pub fn sorted_transitions<T, S>(transitions: &HashMap<T, HashSet<usize>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + std::fmt::Debug,
+    S: Serializer,
+{
+    let mut entries: Vec<(String, &T, Vec<usize>)> = transitions.iter()
+        .map(|(v, targets)| {
+            let mut targets: Vec<usize> = targets.iter().copied().collect();
+            targets.sort_unstable();
+            (format!("{:?}", v), v, targets)
+        })
+        .collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let mut map = serializer.serialize_map(Some(entries.len()))?;
+    for (_, v, targets) in entries.iter() {
+        map.serialize_entry(v, targets)?;
+    }
+    map.end()
+}
@@ -1,3 +1,5 @@
+use super::state_set::StateSet;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
@@ -21,11 +23,14 @@ impl Error for StateMachineError
 {}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct State<T>
 where
     T: Eq + std::hash::Hash + std::fmt::Debug,
 {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "super::ordered::sorted_transitions", bound(serialize = "T: serde::Serialize")))]
     transitions: HashMap<T, HashSet<usize>>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "super::ordered::sorted_set"))]
     eps_transitions: HashSet<usize>,
 }
 
@@ -43,7 +48,7 @@ where
     fn add_transition(&mut self, to: usize, v: T) {
         self.transitions
             .entry(v)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(to);
     }
 
@@ -51,14 +56,73 @@ where
         self.eps_transitions
             .insert(to);
     }
+
+    // Drops every transition to removed and shifts the targets above it down by one
+    fn renumber_after_removal(&mut self, removed: usize) {
+        let shift = |targets: &HashSet<usize>| -> HashSet<usize> {
+            targets
+                .iter()
+                .filter(|to| **to != removed)
+                .map(|to| if *to > removed { to - 1 } else { *to })
+                .collect()
+        };
+        for targets in self.transitions.values_mut() {
+            *targets = shift(targets);
+        }
+        self.transitions.retain(|_, targets| !targets.is_empty());
+        self.eps_transitions = shift(&self.eps_transitions);
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "LoadedStateMachine<T>", bound(deserialize = "T: serde::Deserialize<'de>")))]
 pub struct StateMachine<T>
 where
     T: Eq + std::hash::Hash + std::fmt::Debug,
 {
     states: Vec<State<T>>,
+    // Epsilon closure of every state, built by finalize() and dropped whenever states
+    // or epsilon transitions change (symbol transitions do not affect it)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    eps_closures: Option<Vec<StateSet>>,
+}
+
+// What a serialized StateMachine holds, checked before becoming one since a transition to a missing state would panic on read
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct LoadedStateMachine<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug,
+{
+    states: Vec<State<T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<LoadedStateMachine<T>> for StateMachine<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug,
+{
+    type Error = StateMachineError;
+
+    fn try_from(loaded: LoadedStateMachine<T>) -> Result<Self, Self::Error> {
+        let nb_state = loaded.states.len();
+        for state in &loaded.states {
+            if let Some(to) = state.transitions.values().flatten().chain(&state.eps_transitions).find(|to| **to >= nb_state) {
+                return Err(StateMachineError::InvalidStateId(*to));
+            }
+        }
+        Ok(StateMachine { states: loaded.states, eps_closures: None })
+    }
+}
+
+impl<T> Default for StateMachine<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug,
+{
+    fn default() -> Self {
+        StateMachine::new()
+    }
 }
 
 impl<T> StateMachine<T>
@@ -68,10 +132,12 @@ where
     pub fn new() -> StateMachine<T> {
         StateMachine {
             states: Vec::new(),
+            eps_closures: None,
         }
     }
 
     pub fn add_state(&mut self) -> usize {
+        self.eps_closures = None;
         self.states.push(State::new());
         self.nb_state()-1
     }
@@ -99,37 +165,123 @@ where
         }
     }
 
+    // Adds a new state and a transition from from to it, returning the new id, e.g. to build a chain
+    // Fails without adding anything if from is not a state of self
+    pub fn link(&mut self, from: usize, v: T) -> Result<usize, StateMachineError> {
+        if !self.is_valid_state_id(from) {
+            return Err(StateMachineError::InvalidStateId(from));
+        }
+        let to = self.add_state();
+        self.states[from].add_transition(to, v);
+        Ok(to)
+    }
+
     pub fn add_eps_transition(&mut self, from: usize, to: usize) -> Result<(), StateMachineError> {
         if !self.is_valid_state_id(from) {
             Err(StateMachineError::InvalidStateId(from))
         } else if !self.is_valid_state_id(to) {
             Err(StateMachineError::InvalidStateId(to))
         } else {
+            self.eps_closures = None;
             self.states[from].add_eps_transition(to);
             Ok(())
         }
     }
 
-    pub fn apply_transition(&self, from: usize, v: &T) -> HashSet<usize> {
-        let start = self.apply_eps_transition(from);
+    // Removes the state id along with every transition to it
+    // States of higher ids are renumbered down by one
+    pub fn remove_state(&mut self, id: usize) -> Result<(), StateMachineError> {
+        if !self.is_valid_state_id(id) {
+            return Err(StateMachineError::InvalidStateId(id));
+        }
+        self.eps_closures = None;
+        self.states.remove(id);
+        for st in self.states.iter_mut() {
+            st.renumber_after_removal(id);
+        }
+        Ok(())
+    }
 
-        let mut after_symbol = HashSet::new();
-        for st in start {
-            if let Some(next) = self.states[st].transitions.get(v) {
-                after_symbol.extend(next);
+    // Copies every state of other after the states of self, shifting their ids (and the targets
+    // of their transitions) by the returned offset
+    pub fn absorb(&mut self, other: &StateMachine<T>) -> usize
+    where
+        T: Clone,
+    {
+        self.eps_closures = None;
+        let offset = self.nb_state();
+        for st in other.states.iter() {
+            let mut copy = State::new();
+            for (v, targets) in st.transitions.iter() {
+                for to in targets.iter() {
+                    copy.add_transition(to + offset, v.clone());
+                }
             }
+            for to in st.eps_transitions.iter() {
+                copy.add_eps_transition(to + offset);
+            }
+            self.states.push(copy);
         }
+        offset
+    }
 
-        let mut result = HashSet::new();
-        for st in after_symbol {
-            result.extend(self.apply_eps_transition(st));
+    // Precomputes the epsilon closure of every state so that reads no longer walk epsilon transitions
+    pub fn finalize(&mut self) {
+        let closures = (0..self.nb_state())
+            .map(|st| self.eps_closure(st))
+            .collect();
+        self.eps_closures = Some(closures);
+    }
+
+    // Whether the closures computed by finalize() are still cached
+    pub fn is_finalized(&self) -> bool {
+        self.eps_closures.is_some()
+    }
+
+    pub fn apply_transition(&self, from: usize, v: &T) -> HashSet<usize> {
+        let mut from_set = StateSet::new();
+        from_set.insert(from);
+        self.read_set(&from_set, v).to_hash_set()
+    }
+
+    pub fn apply_eps_transition(&self, from: usize) -> HashSet<usize> {
+        self.closure(from).to_hash_set()
+    }
+
+    // Union of apply_transition(st, v) for every st in current, without intermediate sets
+    pub fn read_set(&self, current: &StateSet, v: &T) -> StateSet {
+        let mut result = StateSet::new();
+        for from in current.iter() {
+            for st in self.closure(from).iter() {
+                if let Some(next) = self.states[st].transitions.get(v) {
+                    for to in next.iter() {
+                        result.union_with(&self.closure(*to));
+                    }
+                }
+            }
         }
+        result
+    }
 
+    // Union of apply_eps_transition(st) for every st in current
+    pub fn closure_set(&self, current: &StateSet) -> StateSet {
+        let mut result = StateSet::new();
+        for from in current.iter() {
+            result.union_with(&self.closure(from));
+        }
         result
     }
 
-    pub fn apply_eps_transition(&self, from: usize) -> HashSet<usize> {
-        let mut reachable = HashSet::new();
+    // Cached closure when finalized, computed on the fly otherwise
+    fn closure(&self, from: usize) -> Cow<'_, StateSet> {
+        match &self.eps_closures {
+            Some(closures) => Cow::Borrowed(&closures[from]),
+            None => Cow::Owned(self.eps_closure(from)),
+        }
+    }
+
+    fn eps_closure(&self, from: usize) -> StateSet {
+        let mut reachable = StateSet::new();
         let mut stack = vec![from];
 
         while let Some(st) = stack.pop() {
@@ -140,6 +292,50 @@ where
         reachable
     }
 
+    // Symbol transitions leaving from, without following any epsilon transition
+    pub fn transitions_from(&self, from: usize) -> impl Iterator<Item = (&T, usize)> + '_ {
+        self.states[from].transitions
+            .iter()
+            .flat_map(|(v, targets)| targets.iter().map(move |to| (v, *to)))
+    }
+
+    pub fn eps_transitions_from(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        self.states[from].eps_transitions
+            .iter()
+            .copied()
+    }
+
+    // Every (from, symbol, to) symbol transition of the machine
+    pub fn transitions(&self) -> impl Iterator<Item = (usize, &T, usize)> + '_ {
+        (0..self.states.len())
+            .flat_map(move |from| self.transitions_from(from).map(move |(v, to)| (from, v, to)))
+    }
+
+    // Every (from, to) epsilon transition of the machine
+    pub fn eps_transitions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.states.len())
+            .flat_map(move |from| self.eps_transitions_from(from).map(move |to| (from, to)))
+    }
+
+    // Every state reachable from from in one step, through a symbol or an epsilon transition
+    pub fn successors(&self, from: usize) -> HashSet<usize> {
+        let state = &self.states[from];
+        state.transitions
+            .values()
+            .flatten()
+            .chain(state.eps_transitions.iter())
+            .copied()
+            .collect()
+    }
+
+    // Every symbol labelling at least one transition
+    pub fn symbols(&self) -> HashSet<&T> {
+        self.states
+            .iter()
+            .flat_map(|st| st.transitions.keys())
+            .collect()
+    }
+
     pub fn is_valid_state_id(&self, state_id: usize) -> bool {
         state_id < self.nb_state()
     }
@@ -155,6 +351,13 @@ mod tests {
         assert!(!st.is_valid_state_id(1));
     }
 
+    #[test]
+    fn test_default() {
+        let st: StateMachine<char> = StateMachine::default();
+        assert_eq!(st.nb_state(), 0);
+        assert!(!st.is_finalized());
+    }
+
     #[test]
     fn test_add_state() {
         let mut st: StateMachine<char> = StateMachine::new();
@@ -184,6 +387,26 @@ mod tests {
         assert_eq!(st.add_transition(0, 1, ' '), Ok(()));
     }
 
+    #[test]
+    fn test_link() {
+        let mut st: StateMachine<char> = StateMachine::new();
+        assert_eq!(st.link(0, 'a'), Err(StateMachineError::InvalidStateId(0)));
+        assert_eq!(st.nb_state(), 0);
+        let start = st.add_state();
+        let mut last = start;
+        for c in ['a', 'b', 'c'] {
+            last = st.link(last, c).unwrap();
+        }
+        assert_eq!(st.nb_state(), 4);
+        let mut current = StateSet::new();
+        current.insert(start);
+        for c in ['a', 'b', 'c'] {
+            current = st.read_set(&current, &c);
+        }
+        assert_eq!(current.to_hash_set(), HashSet::from([last]));
+        assert_eq!(st.apply_transition(start, &'b'), HashSet::new());
+    }
+
     #[test]
     fn test_add_eps_transition() {
         let mut st: StateMachine<char> = StateMachine::new();
@@ -221,6 +444,69 @@ mod tests {
         st
     }
 
+    #[test]
+    fn test_remove_state() {
+        let mut st = exemple_state_machine_with_eps();
+        assert_eq!(st.remove_state(4), Err(StateMachineError::InvalidStateId(4)));
+        assert_eq!(st.remove_state(1), Ok(()));
+        assert_eq!(st.nb_state(), 3);
+        // Old states 2 and 3 are now 1 and 2
+        assert_eq!(st.apply_transition(0, &'a'), HashSet::from([0, 1, 2]));
+        assert_eq!(st.apply_transition(2, &'a'), HashSet::from([0, 1, 2]));
+        assert_eq!(st.apply_transition(2, &'b'), HashSet::new());
+        assert_eq!(st.apply_eps_transition(0), HashSet::from([0, 1, 2]));
+    }
+
+    #[test]
+    fn test_finalize() {
+        let mut st = exemple_state_machine_with_eps();
+        let before: Vec<HashSet<usize>> = (0..4).map(|i| st.apply_transition(i, &'a')).collect();
+        st.finalize();
+        assert!(st.eps_closures.is_some());
+        let after: Vec<HashSet<usize>> = (0..4).map(|i| st.apply_transition(i, &'a')).collect();
+        assert_eq!(before, after);
+        assert_eq!(st.apply_eps_transition(0), HashSet::from([0, 2, 3]));
+
+        // Adding an epsilon transition invalidates the cache
+        st.add_eps_transition(1, 0).unwrap();
+        assert!(st.eps_closures.is_none());
+        assert_eq!(st.apply_eps_transition(1), HashSet::from([0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_transitions() {
+        let st = exemple_state_machine();
+        // The duplicated 3 -b-> 1 transition is only stored once
+        assert_eq!(st.transitions().count(), 4);
+        assert_eq!(st.eps_transitions().count(), 0);
+        let triples: HashSet<(usize, char, usize)> = st.transitions().map(|(from, v, to)| (from, *v, to)).collect();
+        assert_eq!(triples, HashSet::from([(0, 'a', 1), (0, 'b', 2), (3, 'a', 2), (3, 'b', 1)]));
+
+        let st = exemple_state_machine_with_eps();
+        assert_eq!(st.transitions().count(), 5);
+        assert_eq!(st.eps_transitions().collect::<HashSet<_>>(), HashSet::from([(0, 3), (3, 2)]));
+    }
+
+    #[test]
+    fn test_absorb() {
+        let mut st = exemple_state_machine();
+        st.finalize();
+        let other = exemple_state_machine_with_eps();
+        let offset = st.absorb(&other);
+        assert_eq!(offset, 4);
+        assert_eq!(st.nb_state(), 8);
+        assert!(!st.is_finalized());
+        let triples: HashSet<(usize, char, usize)> = st.transitions().map(|(from, v, to)| (from, *v, to)).collect();
+        let expected: HashSet<(usize, char, usize)> = exemple_state_machine().transitions()
+            .map(|(from, v, to)| (from, *v, to))
+            .chain(other.transitions().map(|(from, v, to)| (from + offset, *v, to + offset)))
+            .collect();
+        assert_eq!(triples, expected);
+        assert_eq!(st.eps_transitions().collect::<HashSet<_>>(), HashSet::from([(4, 7), (7, 6)]));
+        // Absorbed states only lead to absorbed states
+        assert_eq!(st.apply_transition(4, &'b'), HashSet::from([5, 6]));
+    }
+
     #[test]
     fn test_apply_transition() {
         let st = exemple_state_machine();
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+const BITS: usize = u64::BITS as usize;
+
+// Set of state ids stored as a bitset, cheaper than a HashSet<usize> for the small dense ids of an automaton
+#[derive(Debug, Clone, Default)]
+pub struct StateSet {
+    words: Vec<u64>,
+}
+
+impl StateSet {
+    pub fn new() -> StateSet {
+        StateSet {
+            words: Vec::new(),
+        }
+    }
+
+    // Returns true if id was not already in the set
+    pub fn insert(&mut self, id: usize) -> bool {
+        let (word, bit) = (id / BITS, id % BITS);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let absent = self.words[word] & (1 << bit) == 0;
+        self.words[word] |= 1 << bit;
+        absent
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.words
+            .get(id / BITS)
+            .is_some_and(|word| word & (1 << (id % BITS)) != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    // Removes every id while keeping the allocation
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+
+    pub fn union_with(&mut self, other: &StateSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (word, other_word) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other_word;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(i, word)| {
+                (0..BITS)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| i * BITS + bit)
+            })
+    }
+
+    pub fn to_hash_set(&self) -> HashSet<usize> {
+        self.iter().collect()
+    }
+}
+
+impl FromIterator<usize> for StateSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut out = StateSet::new();
+        out.extend(iter);
+        out
+    }
+}
+
+impl Extend<usize> for StateSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_contains() {
+        let mut set = StateSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert(3));
+        assert!(set.insert(130));
+        assert!(!set.insert(3));
+        assert!(set.contains(3));
+        assert!(set.contains(130));
+        assert!(!set.contains(4));
+        assert!(!set.contains(1000));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 130]);
+        assert_eq!(set.len(), 2);
+        set.clear();
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn test_union_with() {
+        let mut left: StateSet = [1, 64, 65].into_iter().collect();
+        let right: StateSet = [1, 2, 200].into_iter().collect();
+        left.union_with(&right);
+        assert_eq!(left.to_hash_set(), HashSet::from([1, 2, 64, 65, 200]));
+    }
+}
@@ -0,0 +1,185 @@
+use super::Automaton;
+use std::collections::VecDeque;
+
+impl<T> Automaton<T>
+where
+    T: Eq + std::hash::Hash + std::fmt::Debug + Copy,
+{
+    // Accepted words of at most max_len symbols, shortest first and in increasing symbol order within a length
+    // Depth first search of each length in turn over the minimal DFA, only following the transitions that can still
+    // end on an exit after the symbols left: each word comes out once, every path explored leads to one, and only
+    // the current path is kept, so the first words come out right away however many there are
+    // The number of words can grow exponentially with max_len, callers are expected to take() what they need
+    pub fn words(&self, max_len: usize) -> impl Iterator<Item = Vec<T>>
+    where
+        T: Ord,
+    {
+        let dfa = self.minimize();
+        let nb_state = dfa.machine.nb_state();
+        let next: Vec<Vec<(T, usize)>> = (0..nb_state)
+            .map(|st| {
+                let mut transitions: Vec<(T, usize)> = dfa.machine.transitions_from(st).map(|(v, to)| (*v, to)).collect();
+                transitions.sort_unstable();
+                transitions
+            })
+            .collect();
+        // The minimal DFA has at most one entry
+        let entry = dfa.entry.iter().next().copied();
+        // ends_in[r][st] tells whether an exit is reached from st after exactly r symbols, grown one length at a time
+        let mut ends_in: Vec<Vec<bool>> = vec![(0..nb_state).map(|st| dfa.exit.contains(&st)).collect()];
+        let mut len = 0;
+        // States along the current path with the index of the next transition to try from each of them,
+        // word holding the symbols read from the entry to the last one
+        let mut path: Vec<(usize, usize)> = entry.into_iter().filter(|st| ends_in[0][*st]).map(|st| (st, 0)).collect();
+        let mut word = Vec::new();
+        std::iter::from_fn(move || {
+            loop {
+                let Some(&mut (st, ref mut tried)) = path.last_mut() else {
+                    // Every state is reachable from the entry, so once no state ends on an exit in len symbols
+                    // no longer word is accepted
+                    if len == max_len || !ends_in[len].contains(&true) {
+                        return None;
+                    }
+                    let longer = (0..nb_state)
+                        .map(|st| next[st].iter().any(|(_, to)| ends_in[len][*to]))
+                        .collect();
+                    ends_in.push(longer);
+                    len += 1;
+                    let start = entry?;
+                    if ends_in[len][start] {
+                        path.push((start, 0));
+                    }
+                    continue;
+                };
+                let left = len - word.len();
+                if left == 0 {
+                    let accepted = word.clone();
+                    path.pop();
+                    word.pop();
+                    return Some(accepted);
+                }
+                let (transitions, viable) = (&next[st], &ends_in[left - 1]);
+                let mut i = *tried;
+                while i < transitions.len() && !viable[transitions[i].1] {
+                    i += 1;
+                }
+                if let Some(&(v, to)) = transitions.get(i) {
+                    *tried = i + 1;
+                    word.push(v);
+                    path.push((to, 0));
+                } else {
+                    path.pop();
+                    word.pop();
+                }
+            }
+        })
+    }
+
+    // One of the shortest accepted words, None if no word is accepted
+    // Breadth first search where epsilon transitions cost nothing (0-1 BFS), the path to the first exit reached being the word
+    pub fn shortest_word(&self) -> Option<Vec<T>>
+    where
+        T: Ord,
+    {
+        let nb_state = self.machine.nb_state();
+        let mut dist = vec![usize::MAX; nb_state];
+        // State and symbol (None for an epsilon transition) each state was best reached from
+        let mut parent: Vec<Option<(usize, Option<T>)>> = vec![None; nb_state];
+        let mut settled = vec![false; nb_state];
+        let mut entries: Vec<usize> = self.entry.iter().copied().filter(|st| *st < nb_state).collect();
+        entries.sort_unstable();
+        let mut todo = VecDeque::new();
+        for st in entries {
+            dist[st] = 0;
+            todo.push_back(st);
+        }
+
+        while let Some(st) = todo.pop_front() {
+            if settled[st] {
+                continue;
+            }
+            settled[st] = true;
+            if self.exit.contains(&st) {
+                let mut word = Vec::with_capacity(dist[st]);
+                let mut current = st;
+                while let Some((from, v)) = parent[current] {
+                    word.extend(v);
+                    current = from;
+                }
+                word.reverse();
+                return Some(word);
+            }
+            let mut eps_targets: Vec<usize> = self.machine.eps_transitions_from(st).collect();
+            eps_targets.sort_unstable();
+            for to in eps_targets {
+                if dist[st] < dist[to] {
+                    dist[to] = dist[st];
+                    parent[to] = Some((st, None));
+                    todo.push_front(to);
+                }
+            }
+            let mut transitions: Vec<(&T, usize)> = self.machine.transitions_from(st).collect();
+            transitions.sort_unstable();
+            for (v, to) in transitions {
+                if dist[st] + 1 < dist[to] {
+                    dist[to] = dist[st] + 1;
+                    parent[to] = Some((st, Some(*v)));
+                    todo.push_back(to);
+                }
+            }
+        }
+        None
+    }
+
+    // Number of distinct accepted words of exactly len symbols, saturating at u64::MAX
+    // Counts paths from the entry of the DFA, where each word has at most one path
+    pub fn count_words(&self, len: usize) -> u64 {
+        let dfa = self.to_dfa();
+        let mut paths = vec![0u64; dfa.machine.nb_state()];
+        for st in dfa.entry.iter() {
+            paths[*st] = 1;
+        }
+        for _ in 0..len {
+            let mut next = vec![0u64; paths.len()];
+            for (from, _, to) in dfa.machine.transitions() {
+                next[to] = next[to].saturating_add(paths[from]);
+            }
+            paths = next;
+        }
+        dfa.exit.iter().fold(0u64, |total, st| total.saturating_add(paths[*st]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vregex::Vregex;
+
+    #[test]
+    fn test_words() {
+        let autom = Vregex::new("b*+a").unwrap().automaton;
+        let words: Vec<String> = autom.words(2).map(|w| w.into_iter().collect()).collect();
+        assert_eq!(words, vec!["", "a", "b", "bb"]);
+        assert_eq!(Vregex::new("[^a-zA-Z0-9]").unwrap().automaton.words(3).count(), 0);
+        let words: Vec<String> = Vregex::new("(a+b)c?").unwrap().automaton.words(5).map(|w| w.into_iter().collect()).collect();
+        assert_eq!(words, vec!["a", "b", "ac", "bc"]);
+    }
+
+    #[test]
+    fn test_words_are_lazy() {
+        // 62^6 words of length 6, the first ones come out without going through the others
+        let first: Vec<String> = Vregex::new(".{6}").unwrap().enumerate(6).take(5).collect();
+        assert_eq!(first, vec!["000000", "000001", "000002", "000003", "000004"]);
+        // No word is longer than ab, so a huge max_len ends the search right after it
+        assert_eq!(Vregex::new("ab").unwrap().automaton.words(usize::MAX).count(), 1);
+    }
+
+    #[test]
+    fn test_count_words() {
+        let autom = Vregex::new("(a+b)(a+b)").unwrap().automaton;
+        assert_eq!(autom.count_words(2), 4);
+        assert_eq!(autom.count_words(1), 0);
+        // Both branches accept "ab", which is counted once
+        assert_eq!(Vregex::new("ab+a.").unwrap().automaton.count_words(2), 62);
+        assert_eq!(Vregex::new(".{100}").unwrap().automaton.count_words(100), u64::MAX);
+    }
+}
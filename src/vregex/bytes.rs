@@ -0,0 +1,63 @@
+use super::automaton::Automaton;
+use super::{build_automaton, regex_parsing, BuildContext, VregexError};
+use std::collections::{HashMap, HashSet};
+
+// Regex over raw bytes, same syntax as Vregex with \xHH escapes for bytes outside letters and digits
+// There is no universe to pick, . and negated classes range over all 256 bytes
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ByteRegex {
+    pub automaton: Automaton<u8>,
+}
+
+impl ByteRegex {
+    pub fn new(regex: &str) -> Result<ByteRegex, VregexError> {
+        let reg = regex_parsing::parse_regex(regex)?;
+        // Anchors only matter to searches, validate_bytes() reading the whole input anyway
+        let body = match reg.split_anchors() {
+            (_, Some(body), _) => body.clone(),
+            (_, None, _) => regex_parsing::Regex::Epsilon,
+        };
+        let universe: HashSet<u8> = (0..=u8::MAX).collect();
+        let mut automaton = Automaton::new();
+        // Groups only matter to Vregex::captures() so the tags are dropped
+        let mut ctx = BuildContext {
+            universe: &universe,
+            tags: HashMap::new(),
+            state_limit: None,
+            dot_matches_newline: true,
+        };
+        build_automaton(&mut automaton, body, &mut ctx)?;
+        automaton.finalize();
+        Ok(ByteRegex { automaton })
+    }
+
+    // Whole input match, bytes are never decoded so invalid UTF-8 is fine
+    pub fn validate_bytes(&self, s: &[u8]) -> bool {
+        self.automaton.cursor().validate_seq(s).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_prefix() {
+        let gzip = ByteRegex::new("\\x1F\\x8B.*").unwrap();
+        assert!(gzip.validate_bytes(&[0x1f, 0x8b]));
+        assert!(gzip.validate_bytes(&[0x1f, 0x8b, 0x08, 0x00, 0xff]));
+        assert!(!gzip.validate_bytes(&[0x1f]));
+        assert!(!gzip.validate_bytes(&[0x8b, 0x1f, 0x08]));
+        assert!(!gzip.validate_bytes(b"PK\x03\x04"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let re = ByteRegex::new("\\x41b[\\x00-\\x1f]").unwrap();
+        assert!(re.validate_bytes(b"Ab\n"));
+        assert!(!re.validate_bytes(b"Ab "));
+        assert!(ByteRegex::new("\\x4").is_err());
+        assert!(ByteRegex::new("\\xg1").is_err());
+    }
+}
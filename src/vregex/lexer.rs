@@ -0,0 +1,117 @@
+use super::automaton::Automaton;
+use super::{Vregex, VregexError};
+use std::collections::HashMap;
+
+// Token found by Lexer::next_token(), spanning the bytes start..end of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub name: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Tokenizer over named patterns, all compiled into the union of their automata
+// The exits of each pattern are kept apart to tell which one a token matched
+#[derive(Debug)]
+pub struct Lexer {
+    automaton: Automaton<char>,
+    names: Vec<String>,
+    // Index in names of the pattern each exit state comes from
+    patterns: HashMap<usize, usize>,
+}
+
+impl Lexer {
+    // Takes (name, regex) pairs, e.g. [("num", "[0-9]{1,}"), ("id", "[a-z]{1,}")]
+    // Fails like Vregex::new() on the first invalid regex
+    pub fn new(patterns: &[(&str, &str)]) -> Result<Lexer, VregexError> {
+        let mut automaton = Automaton::new();
+        let mut exits = HashMap::new();
+        for (index, (_, regex)) in patterns.iter().enumerate() {
+            let pattern = Vregex::new(regex)?.automaton;
+            // union() keeps the states of its left side first, so the new ones come after them
+            let offset = automaton.nb_state();
+            exits.extend(pattern.exit_iter().map(|st| (st + offset, index)));
+            automaton = automaton.union(&pattern);
+        }
+        automaton.finalize();
+        Ok(Lexer {
+            automaton,
+            names: patterns.iter().map(|(name, _)| name.to_string()).collect(),
+            patterns: exits,
+        })
+    }
+
+    // Leftmost-longest non-empty token of input starting at or after the byte index *pos, which is moved to its end
+    // When several patterns match that token the first declared one names it
+    // Returns None and leaves *pos as is if no token is left
+    pub fn next_token(&self, input: &str, pos: &mut usize) -> Option<Token<'_>> {
+        let starts = input[*pos..].char_indices().map(|(i, _)| *pos + i);
+        for start in starts {
+            if let Some((end, index)) = self.longest_token_at(input, start) {
+                *pos = end;
+                return Some(Token { name: &self.names[index], start, end });
+            }
+        }
+        None
+    }
+
+    // End and pattern of the longest non-empty token starting at the byte index start
+    fn longest_token_at(&self, input: &str, start: usize) -> Option<(usize, usize)> {
+        let mut cursor = self.automaton.cursor();
+        cursor.init_for_read().ok()?;
+        cursor.read_empty();
+        let mut token = None;
+        for (i, c) in input[start..].char_indices() {
+            cursor.read(&c);
+            if !cursor.is_alive() {
+                break;
+            }
+            let first_pattern = cursor.exits_reached()
+                .iter()
+                .filter_map(|st| self.patterns.get(st))
+                .min()
+                .copied();
+            if let Some(index) = first_pattern {
+                token = Some((start + i + c.len_utf8(), index));
+            }
+        }
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names<'a>(lexer: &'a Lexer, input: &str) -> Vec<&'a str> {
+        let mut pos = 0;
+        let mut out = Vec::new();
+        while let Some(token) = lexer.next_token(input, &mut pos) {
+            out.push(token.name);
+        }
+        out
+    }
+
+    #[test]
+    fn test_next_token() {
+        let lexer = Lexer::new(&[("num", "0"), ("id", "a")]).unwrap();
+        let mut pos = 0;
+        assert_eq!(lexer.next_token("a0", &mut pos), Some(Token { name: "id", start: 0, end: 1 }));
+        assert_eq!(lexer.next_token("a0", &mut pos), Some(Token { name: "num", start: 1, end: 2 }));
+        assert_eq!(lexer.next_token("a0", &mut pos), None);
+        assert_eq!(pos, 2);
+        // Characters no pattern matches are skipped
+        assert_eq!(names(&lexer, "a b a0"), vec!["id", "id", "num"]);
+        assert!(Lexer::new(&[("bad", "a(")]).is_err());
+    }
+
+    #[test]
+    fn test_declaration_order() {
+        let keywords_first = Lexer::new(&[("kw", "if"), ("id", "[a-z]{1,}")]).unwrap();
+        assert_eq!(names(&keywords_first, "if"), vec!["kw"]);
+        // The longest token wins over the declaration order
+        assert_eq!(names(&keywords_first, "iff"), vec!["id"]);
+        let ids_first = Lexer::new(&[("id", "[a-z]{1,}"), ("kw", "if")]).unwrap();
+        assert_eq!(names(&ids_first, "if"), vec!["id"]);
+    }
+}
@@ -1,49 +1,624 @@
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+use winnow::ascii::dec_uint;
 use winnow::combinator::*;
 use winnow::Result;
-use winnow::token::one_of;
+use winnow::stream::AsChar;
+use winnow::token::{one_of, take_while};
 use winnow::Parser;
+use std::collections::HashSet;
+use std::fmt;
 
-pub fn parse_regex(input: &str) -> Result<Regex, String> {
-    let mut s = input;
-    expr.parse_next(&mut s)
-        .map_err(|e| format!("parse error: {:?}", e))
+// Where and why a regex failed to parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    // Byte offset in the regex
+    pub offset: usize,
+    pub message: String,
 }
 
-#[derive(Clone, Debug)]
+// The whole input must be a regex, trailing characters are an error
+// The empty regex is Epsilon, like ()
+pub fn parse_regex(input: &str) -> Result<Regex, ParseError> {
+    let reg = anchored.parse(input)
+        .map(|mut reg| {
+            reg.number_groups(&mut 0);
+            reg
+        })
+        .map_err(|e| {
+            let offset = e.offset();
+            let next = input[offset..].chars().next();
+            // Failing at the end or on a ')' is what an unbalanced parenthesis looks like, so it is pointed at instead
+            if matches!(next, None | Some(')')) {
+                if let Some((offset, paren)) = unmatched_paren(input) {
+                    return ParseError { offset, message: format!("unmatched '{}'", paren) };
+                }
+            }
+            let message = match next {
+                Some(c) => format!("unexpected character '{}'", c),
+                None => "unexpected end of regex".to_string(),
+            };
+            ParseError { offset, message }
+        })?;
+    // The second group given a name is the one pointed at
+    if let Some(name) = reg.duplicate_group_name() {
+        let offset = input.match_indices(&format!("(?<{}>", name)).nth(1).map_or(0, |(offset, _)| offset);
+        return Err(ParseError { offset, message: format!("duplicate group name '{}'", name) });
+    }
+    Ok(reg)
+}
+
+// Same as parse_regex() but unescaped spaces and tabs are ignored, so that long regexes can be laid out,
+// e.g. "( ab )+ c" is (ab)+c, while "\\ " is a literal space (and "\\" followed by a tab a literal tab)
+// Error offsets are in input, whitespace included
+pub fn parse_regex_extended(input: &str) -> Result<Regex, ParseError> {
+    let mut stripped = String::with_capacity(input.len());
+    // Offset in input of every byte of stripped, plus one for its end
+    let mut origins = Vec::with_capacity(input.len() + 1);
+    let mut keep = |text: &str, origin: usize| {
+        stripped.push_str(text);
+        origins.extend(std::iter::repeat_n(origin, text.len()));
+    };
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | '\t' => {}
+            '\\' => {
+                keep("\\", i);
+                match chars.next() {
+                    Some((j, ' ')) => keep("x20", j),
+                    Some((j, '\t')) => keep("x09", j),
+                    Some((j, escaped)) => keep(escaped.encode_utf8(&mut [0; 4]), j),
+                    None => {}
+                }
+            }
+            c => keep(c.encode_utf8(&mut [0; 4]), i),
+        }
+    }
+    origins.push(input.len());
+    parse_regex(&stripped).map_err(|e| ParseError { offset: origins[e.offset], message: e.message })
+}
+
+// Offset of the first ')' closing nothing, or else of the first '(' never closed
+// Escaped parentheses and the content of classes are skipped
+fn unmatched_paren(input: &str) -> Option<(usize, char)> {
+    let mut open = Vec::new();
+    let mut in_class = false;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '(' if !in_class => open.push(i),
+            ')' if !in_class && open.pop().is_none() => return Some((i, ')')),
+            _ => {}
+        }
+    }
+    open.first().map(|i| (*i, '('))
+}
+
+// Syntax tree of a regex, either parsed or built by hand for Vregex::from_ast()
+#[derive(Clone, Debug, PartialEq)]
 pub enum Regex {
     Literal(char),
+    Class(Vec<(char, char)>),
+    NegatedClass(Vec<(char, char)>),
+    AnyChar,
+    // Matches only the empty string, written ()
+    Epsilon,
+    // Zero-width assertions written ^ and $, only allowed at the start and at the end of the regex
+    // where they pin its matches to the start or the end of the searched string, see split_anchors()
+    StartAnchor,
+    EndAnchor,
+    // Parenthesized regex, capturing what it matches as the group of that index
+    // Groups are numbered from 1 in the order of their opening parenthesis
+    Group(usize, Box<Regex>),
+    // Group written (?<name>...), numbered along with the other groups and also reachable by its name
+    NamedGroup(String, usize, Box<Regex>),
     Concat(Box<Regex>, Box<Regex>),
     Plus(Box<Regex>, Box<Regex>),
     Star(Box<Regex>),
+    Opt(Box<Regex>),
+    // One or more, written {1,} since + is alternation
+    Plus1(Box<Regex>),
+    Repeat {
+        inner: Box<Regex>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Regex {
+    // Every character written in the regex, either as a literal or inside a class
+    pub fn symbols(&self) -> HashSet<char> {
+        let mut out = HashSet::new();
+        self.collect_symbols(&mut out);
+        out
+    }
+
+    fn collect_symbols(&self, out: &mut HashSet<char>) {
+        match self {
+            Regex::Literal(c) => {
+                out.insert(*c);
+            }
+            Regex::Class(ranges) | Regex::NegatedClass(ranges) => {
+                for (low, high) in ranges.iter() {
+                    out.extend(*low..=*high);
+                }
+            }
+            Regex::Concat(left, right) | Regex::Plus(left, right) => {
+                left.collect_symbols(out);
+                right.collect_symbols(out);
+            }
+            Regex::Group(_, inner) | Regex::NamedGroup(_, _, inner) | Regex::Star(inner) | Regex::Opt(inner) | Regex::Plus1(inner)
+                | Regex::Repeat { inner, .. } => {
+                inner.collect_symbols(out);
+            }
+            Regex::AnyChar | Regex::Epsilon | Regex::StartAnchor | Regex::EndAnchor => {}
+        }
+    }
+
+    // Highest group index in the tree, 0 if there is no group
+    pub fn max_group(&self) -> usize {
+        match self {
+            Regex::Group(index, inner) | Regex::NamedGroup(_, index, inner) => (*index).max(inner.max_group()),
+            Regex::Concat(left, right) | Regex::Plus(left, right) => left.max_group().max(right.max_group()),
+            Regex::Star(inner) | Regex::Opt(inner) | Regex::Plus1(inner) | Regex::Repeat { inner, .. } => inner.max_group(),
+            Regex::Literal(_) | Regex::Class(_) | Regex::NegatedClass(_) | Regex::AnyChar | Regex::Epsilon
+                | Regex::StartAnchor | Regex::EndAnchor => 0,
+        }
+    }
+
+    // Name and index of every named group, in the order of their opening parenthesis
+    pub fn group_names(&self) -> Vec<(&str, usize)> {
+        let mut out = Vec::new();
+        self.collect_group_names(&mut out);
+        out
+    }
+
+    fn collect_group_names<'a>(&'a self, out: &mut Vec<(&'a str, usize)>) {
+        match self {
+            Regex::NamedGroup(name, index, inner) => {
+                out.push((name, *index));
+                inner.collect_group_names(out);
+            }
+            Regex::Concat(left, right) | Regex::Plus(left, right) => {
+                left.collect_group_names(out);
+                right.collect_group_names(out);
+            }
+            Regex::Group(_, inner) | Regex::Star(inner) | Regex::Opt(inner) | Regex::Plus1(inner) | Regex::Repeat { inner, .. } => {
+                inner.collect_group_names(out);
+            }
+            Regex::Literal(_) | Regex::Class(_) | Regex::NegatedClass(_) | Regex::AnyChar | Regex::Epsilon
+                | Regex::StartAnchor | Regex::EndAnchor => {}
+        }
+    }
+
+    // First name given to two groups, if any
+    pub fn duplicate_group_name(&self) -> Option<&str> {
+        let mut seen = HashSet::new();
+        self.group_names().into_iter().map(|(name, _)| name).find(|name| !seen.insert(*name))
+    }
+
+    // Gives groups the indices following next, in the order of their opening parenthesis
+    fn number_groups(&mut self, next: &mut usize) {
+        match self {
+            Regex::Group(index, inner) | Regex::NamedGroup(_, index, inner) => {
+                *next += 1;
+                *index = *next;
+                inner.number_groups(next);
+            }
+            Regex::Concat(left, right) | Regex::Plus(left, right) => {
+                left.number_groups(next);
+                right.number_groups(next);
+            }
+            Regex::Star(inner) | Regex::Opt(inner) | Regex::Plus1(inner) | Regex::Repeat { inner, .. } => {
+                inner.number_groups(next);
+            }
+            Regex::Literal(_) | Regex::Class(_) | Regex::NegatedClass(_) | Regex::AnyChar | Regex::Epsilon
+                | Regex::StartAnchor | Regex::EndAnchor => {}
+        }
+    }
+
+    // Same tree where every ASCII letter, alone or in a class, also matches its other case
+    // Other characters are left as they are
+    pub fn ignore_ascii_case(self) -> Regex {
+        let fold = |inner: Box<Regex>| Box::new(inner.ignore_ascii_case());
+        match self {
+            Regex::Literal(c) if c.is_ascii_alphabetic() => {
+                let (lower, upper) = (c.to_ascii_lowercase(), c.to_ascii_uppercase());
+                Regex::Class(vec![(lower, lower), (upper, upper)])
+            }
+            Regex::Literal(c) => Regex::Literal(c),
+            Regex::Class(ranges) => Regex::Class(fold_ranges(ranges)),
+            Regex::NegatedClass(ranges) => Regex::NegatedClass(fold_ranges(ranges)),
+            Regex::AnyChar => Regex::AnyChar,
+            Regex::Epsilon => Regex::Epsilon,
+            Regex::StartAnchor => Regex::StartAnchor,
+            Regex::EndAnchor => Regex::EndAnchor,
+            Regex::Concat(left, right) => Regex::Concat(fold(left), fold(right)),
+            Regex::Plus(left, right) => Regex::Plus(fold(left), fold(right)),
+            Regex::Star(inner) => Regex::Star(fold(inner)),
+            Regex::Opt(inner) => Regex::Opt(fold(inner)),
+            Regex::Plus1(inner) => Regex::Plus1(fold(inner)),
+            Regex::Group(index, inner) => Regex::Group(index, fold(inner)),
+            Regex::NamedGroup(name, index, inner) => Regex::NamedGroup(name, index, fold(inner)),
+            Regex::Repeat { inner, min, max } => Regex::Repeat { inner: fold(inner), min, max },
+        }
+    }
+
+    // Whether the regex starts with ^, what is between the anchors (None if nothing) and whether it ends with $,
+    // e.g. (true, Some(a+b), false) for ^a+b
+    // Only recognizes the shapes the parser produces, an anchor anywhere else is left in the body
+    pub fn split_anchors(&self) -> (bool, Option<&Regex>, bool) {
+        let (rest, end) = match self {
+            Regex::EndAnchor => return (false, None, true),
+            Regex::Concat(left, right) if **right == Regex::EndAnchor => (&**left, true),
+            _ => (self, false),
+        };
+        match rest {
+            Regex::StartAnchor => (true, None, end),
+            Regex::Concat(left, right) if **left == Regex::StartAnchor => (true, Some(&**right), end),
+            _ => (false, Some(rest), end),
+        }
+    }
+
+    // Alternation binds loosest, then concatenation, then postfix operators, atoms never need parentheses
+    // Anchors apply to the whole regex, so an anchored one binds as loosely as alternation
+    fn precedence(&self) -> u8 {
+        match self {
+            Regex::Plus(..) => 0,
+            Regex::Concat(..) if !matches!(self.split_anchors(), (false, _, false)) => 0,
+            Regex::Concat(..) => 1,
+            Regex::Star(_) | Regex::Opt(_) | Regex::Plus1(_) | Regex::Repeat { .. } => 2,
+            Regex::Literal(_) | Regex::Class(_) | Regex::NegatedClass(_) | Regex::AnyChar | Regex::Epsilon | Regex::Group(..)
+                | Regex::NamedGroup(..) | Regex::StartAnchor | Regex::EndAnchor => 3,
+        }
+    }
+
+    // Same regex in the syntax of the regex crate (and of most engines), e.g. a|b* for a+b*:
+    // | for alternation, + for {1,}, and (?:) wherever Display adds parentheses that are not a group, so group numbers are kept
+    // Literals and classes are the same, but there . and negated classes match any char (. but \n)
+    // instead of the universe of a Vregex
+    pub fn to_standard_syntax(&self) -> String {
+        StandardSyntax(self).to_string()
+    }
+
+    // Writes self for to_standard_syntax(), in non capturing parentheses if it binds looser than its position requires
+    // Unlike in Display a repetition is wrapped when repeated again, since a*? is a lazy star there and a** an error
+    fn fmt_standard(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence || (*self == Regex::Epsilon && min_precedence > 0) {
+            write!(f, "(?:")?;
+            self.fmt_standard(f, 0)?;
+            return write!(f, ")");
+        }
+        match self {
+            Regex::Group(_, inner) => {
+                write!(f, "(")?;
+                inner.fmt_standard(f, 0)?;
+                write!(f, ")")
+            }
+            Regex::NamedGroup(name, _, inner) => {
+                write!(f, "(?<{}>", name)?;
+                inner.fmt_standard(f, 0)?;
+                write!(f, ")")
+            }
+            Regex::Concat(left, right) => match self.split_anchors() {
+                (false, _, false) => {
+                    left.fmt_standard(f, 1)?;
+                    right.fmt_standard(f, 1)
+                }
+                (start, body, end) => {
+                    if start {
+                        write!(f, "^")?;
+                    }
+                    // ^a|b$ would only anchor a on the left and b on the right
+                    if let Some(body) = body {
+                        body.fmt_standard(f, 1)?;
+                    }
+                    if end {
+                        write!(f, "$")?;
+                    }
+                    Ok(())
+                }
+            },
+            Regex::Plus(left, right) => {
+                left.fmt_standard(f, 0)?;
+                write!(f, "|")?;
+                right.fmt_standard(f, 0)
+            }
+            Regex::Star(inner) => {
+                inner.fmt_standard(f, 3)?;
+                write!(f, "*")
+            }
+            Regex::Opt(inner) => {
+                inner.fmt_standard(f, 3)?;
+                write!(f, "?")
+            }
+            Regex::Plus1(inner) => {
+                inner.fmt_standard(f, 3)?;
+                write!(f, "+")
+            }
+            Regex::Repeat { inner, min, max } => {
+                inner.fmt_standard(f, 3)?;
+                write_bounds(f, *min, *max)
+            }
+            Regex::Literal(_) | Regex::Class(_) | Regex::NegatedClass(_) | Regex::AnyChar | Regex::Epsilon
+                | Regex::StartAnchor | Regex::EndAnchor => write!(f, "{}", self),
+        }
+    }
+
+    // Writes self, in parentheses if it binds looser than its position requires
+    // Epsilon is written as nothing so it always needs them when it is an operand
+    fn fmt_operand(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence || *self == Regex::Epsilon {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+// Canonical source with + for alternation, the parentheses of groups and only the ones precedence requires
+// Parsing a parsed tree back gives the same tree, while the parentheses added for a hand built one become groups
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Regex::Literal(c) => write_symbol(f, *c),
+            Regex::Class(ranges) => write_class(f, "[", ranges),
+            Regex::NegatedClass(ranges) => write_class(f, "[^", ranges),
+            Regex::AnyChar => write!(f, "."),
+            Regex::Epsilon => Ok(()),
+            Regex::StartAnchor => write!(f, "^"),
+            Regex::EndAnchor => write!(f, "$"),
+            Regex::Group(_, inner) => write!(f, "({})", inner),
+            Regex::NamedGroup(name, _, inner) => write!(f, "(?<{}>{})", name, inner),
+            Regex::Concat(left, right) => match self.split_anchors() {
+                (false, _, false) => {
+                    left.fmt_operand(f, 1)?;
+                    right.fmt_operand(f, 1)
+                }
+                (start, body, end) => {
+                    if start {
+                        write!(f, "^")?;
+                    }
+                    if let Some(body) = body {
+                        body.fmt_operand(f, 0)?;
+                    }
+                    if end {
+                        write!(f, "$")?;
+                    }
+                    Ok(())
+                }
+            },
+            Regex::Plus(left, right) => {
+                left.fmt_operand(f, 0)?;
+                write!(f, "+")?;
+                right.fmt_operand(f, 0)
+            }
+            Regex::Star(inner) => {
+                inner.fmt_operand(f, 2)?;
+                write!(f, "*")
+            }
+            Regex::Opt(inner) => {
+                inner.fmt_operand(f, 2)?;
+                write!(f, "?")
+            }
+            Regex::Plus1(inner) => {
+                inner.fmt_operand(f, 2)?;
+                write!(f, "{{1,}}")
+            }
+            Regex::Repeat { inner, min, max } => {
+                inner.fmt_operand(f, 2)?;
+                write_bounds(f, *min, *max)
+            }
+        }
+    }
+}
+
+// Display of a tree through Regex::to_standard_syntax()
+struct StandardSyntax<'a>(&'a Regex);
+
+impl fmt::Display for StandardSyntax<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_standard(f, 0)
+    }
+}
+
+fn write_bounds(f: &mut fmt::Formatter, min: usize, max: Option<usize>) -> fmt::Result {
+    match max {
+        Some(max) if max == min => write!(f, "{{{}}}", min),
+        Some(max) => write!(f, "{{{},{}}}", min, max),
+        None => write!(f, "{{{},}}", min),
+    }
+}
+
+// Adds the other case of every ASCII letter in ranges
+fn fold_ranges(ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+    let swapped: Vec<(char, char)> = ranges.iter()
+        .flat_map(|(low, high)| *low..=*high)
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| if c.is_ascii_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+        .map(|c| (c, c))
+        .collect();
+    let mut out = ranges;
+    out.extend(swapped);
+    out
+}
+
+// Letters and digits as is, metacharacters escaped, other characters below U+0100 as \xHH
+// Characters above have no syntax and are written as is
+fn write_symbol(f: &mut fmt::Formatter, c: char) -> fmt::Result {
+    if c.is_ascii_alphanumeric() {
+        write!(f, "{}", c)
+    } else if METACHARACTERS.contains(&c) {
+        write!(f, "\\{}", c)
+    } else if u32::from(c) < 0x100 {
+        write!(f, "\\x{:02X}", u32::from(c))
+    } else {
+        write!(f, "{}", c)
+    }
+}
+
+fn write_class(f: &mut fmt::Formatter, open: &str, ranges: &[(char, char)]) -> fmt::Result {
+    write!(f, "{}", open)?;
+    for (low, high) in ranges.iter() {
+        write_symbol(f, *low)?;
+        if low != high {
+            write!(f, "-")?;
+            write_symbol(f, *high)?;
+        }
+    }
+    write!(f, "]")
+}
+
+// Letters and digits, every other character being reserved for operators
+fn symbol(input: &mut &str) -> Result<char> {
+    one_of(('a'..='z', 'A'..='Z', '0'..='9'))
+        .parse_next(input)
+}
+
+// Characters with a meaning in a regex, matched literally when preceded by a backslash
+const METACHARACTERS: [char; 15] = ['\\', '*', '+', '|', '?', '(', ')', '[', ']', '{', '}', '.', '^', '$', '-'];
+
+// \* \+ \( \) \\ and so on, or \xHH for the character with that code, a lone or unknown escape being an error
+fn escaped(input: &mut &str) -> Result<char> {
+    preceded('\\', alt((one_of(METACHARACTERS), preceded('x', hex_byte))))
+        .parse_next(input)
+}
+
+// Exactly two hex digits, giving a character between U+0000 and U+00FF
+fn hex_byte(input: &mut &str) -> Result<char> {
+    take_while(2, AsChar::is_hex_digit)
+        .try_map(|digits| u8::from_str_radix(digits, 16))
+        .map(char::from)
+        .parse_next(input)
 }
 
 fn literal(input: &mut &str) -> Result<Regex> {
-    one_of('a'..='z')
+    symbol
         .map(Regex::Literal)
         .parse_next(input)
 }
 
+fn class_symbol(input: &mut &str) -> Result<char> {
+    alt((symbol, escaped))
+        .parse_next(input)
+}
+
+// A single symbol or an inclusive range like a-z, rejecting reversed ranges
+fn class_item(input: &mut &str) -> Result<(char, char)> {
+    (class_symbol, opt(preceded('-', class_symbol)))
+        .verify_map(|(low, high)| match high {
+            None => Some((low, low)),
+            Some(high) if low <= high => Some((low, high)),
+            Some(_) => None,
+        })
+        .parse_next(input)
+}
+
+// [abc], [a-z] or mixed forms like [a-cxy], negated when starting with ^
+fn class(input: &mut &str) -> Result<Regex> {
+    delimited('[', (opt('^'), repeat(1.., class_item)), ']')
+        .map(|(negated, ranges)| match negated {
+            Some(_) => Regex::NegatedClass(ranges),
+            None => Regex::Class(ranges),
+        })
+        .parse_next(input)
+}
+
 fn atom(input: &mut &str) -> Result<Regex> {
+    if input.starts_with('[') {
+        return class.parse_next(input);
+    }
+    if input.starts_with('\\') {
+        return escaped.map(Regex::Literal).parse_next(input);
+    }
+    if input.starts_with('(') {
+        return group.parse_next(input);
+    }
     alt((
-        delimited('(', expr, ')'),
+        '.'.value(Regex::AnyChar),
         literal,
         ))
         .parse_next(input)
 }
 
-fn star(input: &mut &str) -> Result<Regex> {
+// (regex), (?<name>regex), or () for the empty string, numbered once the whole regex is parsed
+fn group(input: &mut &str) -> Result<Regex> {
+    '('.parse_next(input)?;
+    let name = if input.starts_with('?') {
+        Some(group_name.parse_next(input)?)
+    } else {
+        None
+    };
+    let inner = if input.starts_with(')') {
+        Regex::Epsilon
+    } else {
+        expr.parse_next(input)?
+    };
+    ')'.parse_next(input)?;
+    Ok(match name {
+        Some(name) => Regex::NamedGroup(name, 0, Box::new(inner)),
+        None => Regex::Group(0, Box::new(inner)),
+    })
+}
+
+// ?<name> where name is letters, digits and _ not starting with a digit
+fn group_name(input: &mut &str) -> Result<String> {
+    let name = take_while(1.., |c: char| c.is_ascii_alphanumeric() || c == '_')
+        .verify(|name: &str| !name.starts_with(|c: char| c.is_ascii_digit()));
+    delimited("?<", name, '>')
+        .map(str::to_string)
+        .parse_next(input)
+}
+
+// {n}, {n,} or {n,m}, with n <= m
+fn bounds(input: &mut &str) -> Result<(usize, Option<usize>)> {
+    delimited('{', (dec_uint, opt(preceded(',', opt(dec_uint)))), '}')
+        .verify_map(|(min, rest): (usize, Option<Option<usize>>)| match rest {
+            None => Some((min, Some(min))),
+            Some(None) => Some((min, None)),
+            Some(Some(max)) if max >= min => Some((min, Some(max))),
+            Some(Some(_)) => None,
+        })
+        .parse_next(input)
+}
+
+fn postfix(input: &mut &str) -> Result<Regex> {
     let mut node = atom.parse_next(input)?;
-    while opt('*').parse_next(input)?.is_some() {
-        node = Regex::Star(Box::new(node));
+    loop {
+        if let Some(op) = opt(one_of(['*', '?'])).parse_next(input)? {
+            node = match op {
+                '*' => Regex::Star(Box::new(node)),
+                _ => Regex::Opt(Box::new(node)),
+            };
+        } else if input.starts_with('{') {
+            node = match bounds.parse_next(input)? {
+                (1, None) => Regex::Plus1(Box::new(node)),
+                (min, max) => Regex::Repeat { inner: Box::new(node), min, max },
+            };
+        } else {
+            return Ok(node);
+        }
     }
-    Ok(node)
 }
 
+// Whether input starts with a character atom() begins with, i.e. whether a concatenation goes on
+fn starts_atom(input: &str) -> bool {
+    input.chars().next().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '(' | '[' | '\\' | '.'))
+}
+
+// Stops before anything that cannot start an atom (an operator, a closing parenthesis, the end),
+// so a failing atom is always reported instead of silently ending the concatenation
 fn concat(input: &mut &str) -> Result<Regex> {
     let mut nodes = Vec::new();
-    nodes.push(star.parse_next(input)?);
-    while let Ok(next) = star.parse_next(input) {
-        nodes.push(next);
+    nodes.push(postfix.parse_next(input)?);
+    while starts_atom(input) {
+        nodes.push(postfix.parse_next(input)?);
     }
     let mut iter = nodes.iter();
     let mut result = iter.next().unwrap().clone();
@@ -53,9 +628,10 @@ fn concat(input: &mut &str) -> Result<Regex> {
     Ok(result)
 }
 
+// Alternation, written a+b (historical) or a|b (standard)
 fn plus(input: &mut &str) -> Result<Regex> {
     let mut left = concat.parse_next(input)?;
-    while opt('+').parse_next(input)?.is_some() {
+    while opt(one_of(['+', '|'])).parse_next(input)?.is_some() {
         let right = concat.parse_next(input)?;
         left = Regex::Plus(Box::new(left), Box::new(right));
     }
@@ -65,3 +641,186 @@ fn plus(input: &mut &str) -> Result<Regex> {
 fn expr(input: &mut &str) -> Result<Regex> {
     plus.parse_next(input)
 }
+
+// A whole regex: an expression, possibly empty, optionally preceded by ^ and followed by $
+fn anchored(input: &mut &str) -> Result<Regex> {
+    let start = opt('^').parse_next(input)?.is_some();
+    // Checked upfront since a failing expr would otherwise hide where a malformed regex went wrong
+    let mut node = if input.is_empty() || *input == "$" {
+        None
+    } else {
+        Some(expr.parse_next(input)?)
+    };
+    if start {
+        node = Some(match node {
+            Some(body) => Regex::Concat(Box::new(Regex::StartAnchor), Box::new(body)),
+            None => Regex::StartAnchor,
+        });
+    }
+    if opt('$').parse_next(input)?.is_some() {
+        node = Some(match node {
+            Some(body) => Regex::Concat(Box::new(body), Box::new(Regex::EndAnchor)),
+            None => Regex::EndAnchor,
+        });
+    }
+    Ok(node.unwrap_or(Regex::Epsilon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(regex: &str) -> String {
+        let ast = parse_regex(regex).unwrap();
+        let shown = ast.to_string();
+        assert_eq!(parse_regex(&shown).unwrap(), ast, "{} displayed as {}", regex, shown);
+        shown
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        assert_eq!(round_trip("((ab)+c)*"), "((ab)+c)*");
+        assert_eq!(round_trip("(a+b)c"), "(a+b)c");
+        assert_eq!(round_trip("a|b"), "a+b");
+        assert_eq!(round_trip("(ab)*c?"), "(ab)*c?");
+        assert_eq!(round_trip("a{2}b{2,}c{1,3}d{1,}"), "a{2}b{2,}c{1,3}d{1,}");
+        assert_eq!(round_trip("[a-c\\-][^x]."), "[a-c\\-][^x].");
+        assert_eq!(round_trip("\\*\\x20\\x41"), "\\*\\x20A");
+        assert_eq!(round_trip("a()*"), "a()*");
+    }
+
+    #[test]
+    fn test_to_standard_syntax() {
+        // The regex crate compiles every output below and accepts the same strings, . and negated classes aside
+        let standard = |regex: &str| parse_regex(regex).unwrap().to_standard_syntax();
+        assert_eq!(standard("a+b*"), "a|b*");
+        assert_eq!(standard("(a+b)c"), "(a|b)c");
+        assert_eq!(standard("a{1,}(ab){1,}c{2,3}"), "a+(ab)+c{2,3}");
+        assert_eq!(standard("[a-c\\-][^x].\\+\\x20"), "[a-c\\-][^x].\\+\\x20");
+        assert_eq!(standard("a()"), "a()");
+        assert_eq!(standard(""), "");
+        // Kept as a greedy star repeated, and the anchors still apply to the whole alternation
+        assert_eq!(standard("a*?b**"), "(?:a*)?(?:b*)*");
+        assert_eq!(standard("^a+b$"), "^(?:a|b)$");
+        assert_eq!(standard("^ab"), "^ab");
+        // Parentheses a hand built tree needs do not become groups
+        let ast = Regex::Star(Box::new(Regex::Concat(Box::new(Regex::Literal('a')), Box::new(Regex::Literal('b')))));
+        assert_eq!(ast.to_standard_syntax(), "(?:ab)*");
+        let ast = Regex::Plus(Box::new(Regex::Literal('a')), Box::new(Regex::Epsilon));
+        assert_eq!(ast.to_standard_syntax(), "a|");
+    }
+
+    #[test]
+    fn test_display_ast() {
+        let ast = Regex::Concat(Box::new(Regex::Literal('a')), Box::new(Regex::Star(Box::new(Regex::Literal('b')))));
+        assert_eq!(ast.to_string(), "ab*");
+        let ast = Regex::Star(Box::new(Regex::Concat(Box::new(Regex::Literal('a')), Box::new(Regex::Literal('b')))));
+        assert_eq!(ast.to_string(), "(ab)*");
+        let ast = Regex::Plus(Box::new(Regex::Literal('a')), Box::new(Regex::Epsilon));
+        assert_eq!(ast.to_string(), "a+()");
+    }
+
+    #[test]
+    fn test_precedence() {
+        let lit = |c| Box::new(Regex::Literal(c));
+        let star = |c| Box::new(Regex::Star(lit(c)));
+        assert_eq!(
+            parse_regex("a+b*c").unwrap(),
+            Regex::Plus(lit('a'), Box::new(Regex::Concat(star('b'), lit('c')))),
+        );
+        assert_eq!(
+            parse_regex("ab*+c").unwrap(),
+            Regex::Plus(Box::new(Regex::Concat(lit('a'), star('b'))), lit('c')),
+        );
+        assert_eq!(
+            parse_regex("a+b+c").unwrap(),
+            Regex::Plus(Box::new(Regex::Plus(lit('a'), lit('b'))), lit('c')),
+        );
+    }
+
+    #[test]
+    fn test_trailing_operator() {
+        for (regex, offset) in [("a+", 2), ("a|", 2), ("ab+*", 3), ("a+|b", 2), ("a**+", 4), ("+a", 0)] {
+            assert_eq!(parse_regex(regex).unwrap_err().offset, offset, "{}", regex);
+        }
+        assert_eq!(parse_regex("a+").unwrap_err().message, "unexpected end of regex");
+    }
+
+    #[test]
+    fn test_unmatched_parens() {
+        for (regex, offset, message) in [
+            ("ab)", 2, "unmatched ')'"),
+            ("a)b", 1, "unmatched ')'"),
+            ("(a))", 3, "unmatched ')'"),
+            ("a(b", 1, "unmatched '('"),
+            ("((a)", 0, "unmatched '('"),
+            ("(a(", 0, "unmatched '('"),
+        ] {
+            assert_eq!(parse_regex(regex).unwrap_err(), ParseError { offset, message: message.to_string() }, "{}", regex);
+        }
+        // Escaped parentheses do not count
+        assert_eq!(parse_regex("\\(a").unwrap(), Regex::Concat(Box::new(Regex::Literal('(')), Box::new(Regex::Literal('a'))));
+        assert_eq!(parse_regex("(a\\)").unwrap_err().message, "unmatched '('");
+        // Other errors keep their own message
+        assert_eq!(parse_regex("(a{3,1})").unwrap_err().message, "unexpected character '{'");
+    }
+
+    #[test]
+    fn test_extended() {
+        assert_eq!(parse_regex_extended("( ab )+ c").unwrap(), parse_regex("(ab)+c").unwrap());
+        assert_eq!(parse_regex_extended("\ta {2, 3}\t").unwrap(), parse_regex("a{2,3}").unwrap());
+        assert_eq!(parse_regex_extended("a\\ b").unwrap(), parse_regex("a\\x20b").unwrap());
+        assert_eq!(parse_regex_extended("[a -c] \\*").unwrap(), parse_regex("[a-c]\\*").unwrap());
+        assert_eq!(parse_regex_extended("   ").unwrap(), Regex::Epsilon);
+        // Offsets point into the regex as written
+        assert_eq!(parse_regex_extended("a  )").unwrap_err().offset, 3);
+        assert_eq!(parse_regex_extended("a \\ \\q").unwrap_err().offset, 5);
+        assert_eq!(parse_regex_extended("a + ").unwrap_err().offset, 4);
+    }
+
+    #[test]
+    fn test_anchors() {
+        let ast = parse_regex("^a+b$").unwrap();
+        let body = parse_regex("a+b").unwrap();
+        assert_eq!(ast.split_anchors(), (true, Some(&body), true));
+        assert!(!parse_regex("ab$").unwrap().split_anchors().0);
+        assert_eq!(parse_regex("^$").unwrap().split_anchors(), (true, None, true));
+        assert_eq!(round_trip("^a+b$"), "^a+b$");
+        assert_eq!(round_trip("^(a)*"), "^(a)*");
+        assert_eq!(round_trip("$"), "$");
+        assert_eq!(round_trip("\\^\\$"), "\\^\\$");
+        assert_eq!(parse_regex("a^b").unwrap_err().offset, 1);
+        assert!(parse_regex("a$b").is_err());
+        assert!(parse_regex("^^a").is_err());
+    }
+
+    #[test]
+    fn test_group_numbering() {
+        let group = |index, inner| Regex::Group(index, Box::new(inner));
+        let concat = |left, right| Regex::Concat(Box::new(left), Box::new(right));
+        let expected = concat(
+            group(1, concat(Regex::Literal('a'), group(2, Regex::Literal('b')))),
+            group(3, Regex::Literal('c')),
+        );
+        let ast = parse_regex("(a(b))(c)").unwrap();
+        assert_eq!(ast, expected);
+        assert_eq!(ast.max_group(), 3);
+        assert_eq!(parse_regex("ab").unwrap().max_group(), 0);
+    }
+
+    #[test]
+    fn test_named_groups() {
+        let ast = parse_regex("(?<first>a)((?<_2nd>b))").unwrap();
+        assert_eq!(ast.group_names(), vec![("first", 1), ("_2nd", 3)]);
+        assert_eq!(ast.to_string(), "(?<first>a)((?<_2nd>b))");
+        assert_eq!(ast.to_standard_syntax().to_string(), "(?<first>a)((?<_2nd>b))");
+        assert_eq!(parse_regex("(?<x>)").unwrap().to_string(), "(?<x>)");
+
+        let err = parse_regex("(?<x>a)b(?<x>c)").unwrap_err();
+        assert_eq!(err.offset, 8);
+        assert_eq!(err.message, "duplicate group name 'x'");
+        for malformed in ["(?<", "(?<>a)", "(?<1a>b)", "(?<a b>c)", "(?<a", "(?a)", "(?<a>b"] {
+            assert!(parse_regex(malformed).is_err(), "{}", malformed);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use super::Regex;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+// Deepest nesting of operators in a generated tree, which with small repetition bounds keeps automata small
+const MAX_DEPTH: usize = 4;
+
+// Random well formed trees, e.g. to fuzz the parser and the build: Display writes them in a form the parser reads back,
+// groups are numbered like the parser does and anchors only wrap the whole tree
+// Running out of data gives atoms, so any input yields a tree
+impl<'a> Arbitrary<'a> for Regex {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut reg = arbitrary_body(u, MAX_DEPTH)?;
+        if u.arbitrary()? {
+            reg = Regex::Concat(Box::new(Regex::StartAnchor), Box::new(reg));
+        }
+        if u.arbitrary()? {
+            reg = Regex::Concat(Box::new(reg), Box::new(Regex::EndAnchor));
+        }
+        reg.number_groups(&mut 0);
+        Ok(reg)
+    }
+}
+
+fn arbitrary_body(u: &mut Unstructured, depth: usize) -> Result<Regex> {
+    if depth == 0 {
+        return arbitrary_atom(u);
+    }
+    let reg = match u.int_in_range(0..=10)? {
+        0..=2 => arbitrary_atom(u)?,
+        3 => Regex::Epsilon,
+        4 => Regex::Group(0, arbitrary_operand(u, depth)?),
+        5 => Regex::Concat(arbitrary_operand(u, depth)?, arbitrary_operand(u, depth)?),
+        6 => Regex::Plus(arbitrary_operand(u, depth)?, arbitrary_operand(u, depth)?),
+        7 => Regex::Star(arbitrary_operand(u, depth)?),
+        8 => Regex::Opt(arbitrary_operand(u, depth)?),
+        9 => Regex::Plus1(arbitrary_operand(u, depth)?),
+        _ => {
+            let inner = arbitrary_operand(u, depth)?;
+            let min = u.int_in_range(0..=3)?;
+            let max = if u.arbitrary()? { Some(min + u.int_in_range(0..=2)?) } else { None };
+            Regex::Repeat { inner, min, max }
+        }
+    };
+    Ok(reg)
+}
+
+fn arbitrary_operand(u: &mut Unstructured, depth: usize) -> Result<Box<Regex>> {
+    Ok(Box::new(arbitrary_body(u, depth - 1)?))
+}
+
+fn arbitrary_atom(u: &mut Unstructured) -> Result<Regex> {
+    let reg = match u.int_in_range(0..=3)? {
+        0 => Regex::Literal(arbitrary_symbol(u)?),
+        1 => Regex::Class(arbitrary_ranges(u)?),
+        2 => Regex::NegatedClass(arbitrary_ranges(u)?),
+        _ => Regex::AnyChar,
+    };
+    Ok(reg)
+}
+
+// Chars above 0xFF have no \xHH escape, so Display could not write them back
+fn arbitrary_symbol(u: &mut Unstructured) -> Result<char> {
+    Ok(char::from(u8::arbitrary(u)?))
+}
+
+// One to three ranges, none of them reversed
+fn arbitrary_ranges(u: &mut Unstructured) -> Result<Vec<(char, char)>> {
+    let nb_ranges = u.int_in_range(1..=3)?;
+    let mut ranges = Vec::with_capacity(nb_ranges);
+    for _ in 0..nb_ranges {
+        let (a, b) = (arbitrary_symbol(u)?, arbitrary_symbol(u)?);
+        ranges.push((a.min(b), a.max(b)));
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vregex::regex_parsing::parse_regex;
+    use crate::vregex::Vregex;
+
+    #[test]
+    fn test_random_asts() {
+        // xorshift, so that failures can be replayed
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut data = [0u8; 256];
+        for _ in 0..1000 {
+            for byte in data.iter_mut() {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                *byte = (seed >> 32) as u8;
+            }
+            let ast = Regex::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            let shown = ast.to_string();
+            let parsed = parse_regex(&shown).unwrap_or_else(|e| panic!("{} does not parse back: {:?}", shown, e));
+            assert_eq!(parsed.to_string(), shown);
+            // Checking equivalence would determinize, too slow for a thousand trees, so both only have to agree on a few strings
+            let mut built = Vregex::from_ast(ast).unwrap();
+            let mut reparsed = Vregex::from_ast(parsed).unwrap();
+            let shortest = built.shortest_match();
+            for s in ["", "a", "ab\u{0}."].into_iter().chain(shortest.as_deref()) {
+                assert_eq!(built.validate(s).unwrap(), reparsed.validate(s).unwrap(), "{} on {:?}", shown, s);
+            }
+            if let Some(s) = shortest {
+                assert!(built.validate(&s).unwrap(), "{} on {:?}", shown, s);
+            }
+        }
+    }
+}